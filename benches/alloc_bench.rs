@@ -0,0 +1,81 @@
+#![feature(test)]
+
+extern crate intmap;
+extern crate test;
+
+use intmap::IntMap;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::Bencher;
+
+    const COUNT: u64 = 10_000;
+
+    // Fills a fresh map to the resize threshold, so nearly all buckets are touched.
+    fn fill_high_load(bucket_capacity: usize) -> IntMap<u64> {
+        let mut map = IntMap::new();
+        map.set_bucket_capacity(bucket_capacity);
+        map.reserve(COUNT as usize * 10 / 7);
+
+        for i in 0..COUNT {
+            // Scrambled keys, so buckets fill up like they would with random keys.
+            let key = i.wrapping_mul(0x2545_f491_4f6c_dd1d).rotate_left(29);
+            map.insert(key, i);
+        }
+
+        map
+    }
+
+    fn bench_high_load(b: &mut Bencher, bucket_capacity: usize) {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        test::black_box(fill_high_load(bucket_capacity));
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        eprintln!(
+            "bucket_capacity {}: {} allocations for {} inserts",
+            bucket_capacity, allocations, COUNT
+        );
+
+        b.iter(|| test::black_box(fill_high_load(bucket_capacity)));
+    }
+
+    #[bench]
+    fn u64_insert_high_load_bucket_capacity_0(b: &mut Bencher) {
+        bench_high_load(b, 0);
+    }
+
+    #[bench]
+    fn u64_insert_high_load_bucket_capacity_1(b: &mut Bencher) {
+        bench_high_load(b, 1);
+    }
+
+    #[bench]
+    fn u64_insert_high_load_bucket_capacity_2(b: &mut Bencher) {
+        bench_high_load(b, 2);
+    }
+}
@@ -6,7 +6,7 @@ extern crate test;
 
 extern crate ordermap;
 
-use intmap::IntMap;
+use intmap::{ArenaIntMap, IntMap};
 use ordermap::OrderMap;
 use std::collections::HashMap;
 
@@ -117,6 +117,115 @@ mod tests {
         });
     }
 
+    #[bench]
+    fn u64_get_intmap_low_load(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT);
+
+        let mut map = IntMap::with_capacity(data.len() * 16);
+        for s in data.iter() {
+            map.insert(*s, s);
+        }
+
+        b.iter(|| {
+            for s in data.iter() {
+                test::black_box(map.get(*s));
+            }
+        });
+    }
+
+    #[bench]
+    fn u64_get_miss_intmap_low_load(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT * 2);
+        let (present, absent) = data.split_at(VEC_COUNT);
+
+        let mut map = IntMap::with_capacity(present.len() * 16);
+        for s in present.iter() {
+            map.insert(*s, s);
+        }
+
+        b.iter(|| {
+            for s in absent.iter() {
+                test::black_box(map.get(*s));
+            }
+        });
+    }
+
+    #[bench]
+    fn u64_upsert_intmap_entry(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT);
+        let mut map: IntMap<u64> = IntMap::with_capacity(data.len());
+
+        b.iter(|| {
+            map.clear();
+
+            for _ in 0..4 {
+                for s in data.iter() {
+                    *map.entry(*s).or_insert_with(|| 0) += 1;
+                }
+            }
+            test::black_box(&map);
+        });
+    }
+
+    #[bench]
+    fn u64_upsert_intmap_get_mut_or_else_insert_with(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT);
+        let mut map: IntMap<u64> = IntMap::with_capacity(data.len());
+
+        b.iter(|| {
+            map.clear();
+
+            for _ in 0..4 {
+                for s in data.iter() {
+                    *map.get_mut_or_else_insert_with(*s, || 0) += 1;
+                }
+            }
+            test::black_box(&map);
+        });
+    }
+
+    #[bench]
+    fn u64_iter_intmap(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT * 100);
+
+        let mut map: IntMap<[u64; 4]> = IntMap::with_capacity(data.len());
+        for s in data.iter() {
+            map.insert(*s, [*s; 4]);
+        }
+
+        b.iter(|| test::black_box(map.values().map(|v| v[0]).fold(0, u64::wrapping_add)));
+    }
+
+    // ********** ArenaIntMap **********
+
+    #[bench]
+    fn u64_get_arena_intmap(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT);
+
+        let mut map = ArenaIntMap::with_capacity(data.len());
+        for s in data.iter() {
+            map.insert(*s, s);
+        }
+
+        b.iter(|| {
+            for s in data.iter() {
+                test::black_box(map.contains_key(*s));
+            }
+        });
+    }
+
+    #[bench]
+    fn u64_iter_arena_intmap(b: &mut Bencher) {
+        let data = get_random_range(VEC_COUNT * 100);
+
+        let mut map: ArenaIntMap<[u64; 4]> = ArenaIntMap::with_capacity(data.len());
+        for s in data.iter() {
+            map.insert(*s, [*s; 4]);
+        }
+
+        b.iter(|| test::black_box(map.values().map(|v| v[0]).fold(0, u64::wrapping_add)));
+    }
+
     // ********** Misc **********
 
     fn get_random_range(count: usize) -> Vec<u64> {
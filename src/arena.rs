@@ -0,0 +1,272 @@
+use crate::{IntMap, DEFAULT_CAPACITY, HASH_MULTIPLIER};
+
+/// A variant of `IntMap` that stores all values in one contiguous arena.
+///
+/// Buckets only hold a key and the index of its value in the arena, so scanning a bucket
+/// touches 12 bytes per entry no matter how large `V` is, and iterating the map is a
+/// linear scan over the arena instead of a walk over many small allocations. Removed
+/// values leave a hole in the arena which is put on a free list and reused by the next
+/// insert. Once more than half of the arena are holes, the remaining values are moved
+/// together and the arena is shrunk, so its memory follows the number of live entries.
+///
+/// This is a separate type rather than a storage mode of `IntMap`: a mode would change
+/// the bucket type that `IntMap` exposes through `raw_buckets`, and add a branch to every
+/// lookup of maps that do not use it. It only offers the basic map operations.
+///
+/// The arena is indexed by `u32`, so the map holds at most `u32::MAX` entries.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::ArenaIntMap;
+///
+/// let mut map: ArenaIntMap<[u64; 8]> = ArenaIntMap::new();
+/// map.insert(1, [1; 8]);
+/// map.insert(2, [2; 8]);
+///
+/// assert_eq!(map.get(2), Some(&[2; 8]));
+/// assert_eq!(map.remove(1), Some([1; 8]));
+/// assert_eq!(map.len(), 1);
+/// ```
+pub struct ArenaIntMap<V> {
+    cache: Vec<Vec<(u64, u32)>>,
+    size: u32,
+    mod_mask: u64,
+    // Every entry ever inserted, `None` for the holes left by removed entries.
+    arena: Vec<Option<(u64, V)>>,
+    // Indices of the holes in `arena`.
+    free: Vec<u32>,
+}
+
+impl<V> ArenaIntMap<V> {
+    /// Creates a new ArenaIntMap.
+    pub fn new() -> Self {
+        ArenaIntMap::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new ArenaIntMap with at least the given number of slots, rounded to the
+    /// next power of two. The arena reserves room for as many values.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        let mut cache = Vec::with_capacity(capacity);
+        for _ in 0..capacity {
+            cache.push(Vec::new());
+        }
+
+        ArenaIntMap {
+            cache,
+            size: capacity.trailing_zeros(),
+            mod_mask: (capacity as u64) - 1,
+            arena: Vec::with_capacity(capacity),
+            free: Vec::new(),
+        }
+    }
+
+    /// Insert key/value into the ArenaIntMap if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map already holds `u32::MAX` entries.
+    pub fn insert(&mut self, key: u64, value: V) -> bool {
+        if self.contains_key(key) {
+            return false;
+        }
+
+        if ((self.len() + 1) * 100) / self.cache.len() > 70 {
+            self.grow();
+        }
+
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.arena[slot as usize] = Some((key, value));
+                slot
+            }
+            None => {
+                assert!(self.arena.len() < u32::MAX as usize, "arena is full");
+                self.arena.push(Some((key, value)));
+                (self.arena.len() - 1) as u32
+            }
+        };
+
+        let ix = self.calc_index(key);
+        self.cache[ix].push((key, slot));
+
+        true
+    }
+
+    /// Get value from the ArenaIntMap.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let slot = self.find(key)?;
+
+        self.arena[slot].as_ref().map(|kv| &kv.1)
+    }
+
+    /// Get mutable value from the ArenaIntMap.
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let slot = self.find(key)?;
+
+        self.arena[slot].as_mut().map(|kv| &mut kv.1)
+    }
+
+    /// Returns true if key is in map.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Remove value from the ArenaIntMap, putting its place in the arena on the free list.
+    ///
+    /// Compacts the arena when more than half of it are holes, which takes time linear in
+    /// its length but happens rarely enough to be amortized over the removals.
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let ix = self.calc_index(key);
+        let pos = self.cache[ix].iter().position(|kv| kv.0 == key)?;
+
+        let slot = self.cache[ix].swap_remove(pos).1;
+        self.free.push(slot);
+        let value = self.arena[slot as usize].take().map(|kv| kv.1);
+
+        if self.free.len() > self.arena.len() / 2 {
+            self.compact();
+        }
+
+        value
+    }
+
+    /// Removes all elements from the map, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        for vals in self.cache.iter_mut() {
+            vals.clear();
+        }
+        self.arena.clear();
+        self.free.clear();
+    }
+
+    /// Number of elements in map.
+    pub fn len(&self) -> usize {
+        self.arena.len() - self.free.len()
+    }
+
+    /// Returns true if map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total number of slots available.
+    pub fn capacity(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Iterates over all entries in arena order, i.e. in insertion order as long as nothing
+    /// was removed.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &V)> {
+        self.arena
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|kv| (kv.0, &kv.1)))
+    }
+
+    /// Iterates over all entries in arena order with mutable references to the values.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u64, &mut V)> {
+        self.arena
+            .iter_mut()
+            .filter_map(|slot| slot.as_mut().map(|kv| (kv.0, &mut kv.1)))
+    }
+
+    /// Iterates over all values in arena order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|kv| kv.1)
+    }
+
+    fn find(&self, key: u64) -> Option<usize> {
+        let ix = self.calc_index(key);
+
+        self.cache[ix]
+            .iter()
+            .find(|kv| kv.0 == key)
+            .map(|kv| kv.1 as usize)
+    }
+
+    // Moves the values into the holes before them, keeping their order, and frees the
+    // rest of the arena.
+    fn compact(&mut self) {
+        let mut next = 0;
+        for i in 0..self.arena.len() {
+            let kv = match self.arena[i].take() {
+                Some(kv) => kv,
+                None => continue,
+            };
+
+            if i != next {
+                let ix = self.calc_index(kv.0);
+                let entry = self.cache[ix].iter_mut().find(|e| e.0 == kv.0).unwrap();
+                entry.1 = next as u32;
+            }
+            self.arena[next] = Some(kv);
+            next += 1;
+        }
+
+        self.arena.truncate(next);
+        self.arena.shrink_to_fit();
+        self.free.clear();
+        self.free.shrink_to_fit();
+    }
+
+    fn grow(&mut self) {
+        self.size += 1;
+        let new_lim = 1usize << self.size;
+        self.mod_mask = (new_lim as u64) - 1;
+
+        // Only the small bucket entries move, the values stay where they are.
+        let old = std::mem::replace(&mut self.cache, Vec::with_capacity(new_lim));
+        for _ in 0..new_lim {
+            self.cache.push(Vec::new());
+        }
+        for vals in old {
+            for kv in vals {
+                let ix = self.calc_index(kv.0);
+                self.cache[ix].push(kv);
+            }
+        }
+    }
+
+    #[inline]
+    fn calc_index(&self, key: u64) -> usize {
+        (HASH_MULTIPLIER.wrapping_mul(key) & self.mod_mask) as usize
+    }
+}
+
+impl<V> Default for ArenaIntMap<V> {
+    fn default() -> Self {
+        ArenaIntMap::new()
+    }
+}
+
+impl<V> From<IntMap<V>> for ArenaIntMap<V> {
+    fn from(map: IntMap<V>) -> Self {
+        let mut arena = ArenaIntMap::with_capacity(map.capacity());
+        for (k, v) in map {
+            arena.insert(k, v);
+        }
+        arena
+    }
+}
+
+impl<V> std::iter::FromIterator<(u64, V)> for ArenaIntMap<V> {
+    fn from_iter<T: IntoIterator<Item = (u64, V)>>(iter: T) -> Self {
+        let mut map = ArenaIntMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<V> std::fmt::Debug for ArenaIntMap<V>
+where
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,60 @@
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// Values that `IntMap::write_to` and `IntMap::read_from` can encode.
+///
+/// Implemented for all primitive integers and floats, which are written as their
+/// little-endian bytes. `usize` and `isize` are written as `u64` and `i64` so the
+/// output doesn't depend on the platform, reading a value that doesn't fit fails with
+/// `InvalidData`. Only available with the `binary-io` feature.
+pub trait BinaryValue: Sized {
+    /// Writes the value to `w`.
+    fn encode<W: Write>(&self, w: &mut W) -> Result<()>;
+
+    /// Reads a value written by `encode` from `r`.
+    fn decode<R: Read>(r: &mut R) -> Result<Self>;
+}
+
+macro_rules! impl_binary_value {
+    ($($t:ty),*) => {
+        $(
+            impl BinaryValue for $t {
+                fn encode<W: Write>(&self, w: &mut W) -> Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(r: &mut R) -> Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    r.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+macro_rules! impl_binary_value_as {
+    ($($t:ty => $wide:ty),*) => {
+        $(
+            impl BinaryValue for $t {
+                fn encode<W: Write>(&self, w: &mut W) -> Result<()> {
+                    (*self as $wide).encode(w)
+                }
+
+                fn decode<R: Read>(r: &mut R) -> Result<Self> {
+                    let wide = <$wide>::decode(r)?;
+                    <$t>::try_from(wide).map_err(|_| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("{} out of range for {}", wide, stringify!($t)),
+                        )
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value_as!(usize => u64, isize => i64);
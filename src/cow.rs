@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use crate::{IntMap, DEFAULT_CAPACITY, HASH_MULTIPLIER};
+
+type Bucket<V> = Arc<Vec<(u64, V)>>;
+
+/// A copy-on-write variant of `IntMap` with O(1) `clone`.
+///
+/// The bucket table and every bucket are reference counted. Cloning only bumps a
+/// reference count, so clones are cheap snapshots. The first mutation after a clone
+/// copies the table of bucket pointers (not the entries) and deep-copies only the bucket
+/// being modified; all other buckets stay shared. A resize copies every still shared
+/// bucket. Mutation requires `V: Clone` for that reason.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::CowIntMap;
+///
+/// let mut map: CowIntMap<u64> = CowIntMap::new();
+/// map.insert(1, 10);
+///
+/// let snapshot = map.clone();
+/// map.insert(2, 20);
+///
+/// assert_eq!(snapshot.len(), 1);
+/// assert_eq!(map.len(), 2);
+/// ```
+pub struct CowIntMap<V> {
+    cache: Arc<Vec<Bucket<V>>>,
+    size: u32,
+    mod_mask: u64,
+    count: usize,
+}
+
+impl<V> CowIntMap<V> {
+    /// Creates a new CowIntMap.
+    pub fn new() -> Self {
+        CowIntMap::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new CowIntMap with at least the given capacity, rounded to the next power
+    /// of two.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        let mut cache = Vec::with_capacity(capacity);
+        for _ in 0..capacity {
+            cache.push(Arc::new(Vec::new()));
+        }
+
+        CowIntMap {
+            cache: Arc::new(cache),
+            size: capacity.trailing_zeros(),
+            mod_mask: (capacity as u64) - 1,
+            count: 0,
+        }
+    }
+
+    /// Get value from the CowIntMap.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let ix = self.calc_index(key);
+
+        self.cache[ix].iter().find(|kv| kv.0 == key).map(|kv| &kv.1)
+    }
+
+    /// Returns true if key is in map.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of elements in map.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Total number of slots available.
+    pub fn capacity(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &V)> {
+        self.cache
+            .iter()
+            .flat_map(|vals| vals.iter().map(|kv| (kv.0, &kv.1)))
+    }
+
+    /// Returns true if both maps still share the same, unmodified bucket table.
+    pub fn ptr_eq(&self, other: &CowIntMap<V>) -> bool {
+        Arc::ptr_eq(&self.cache, &other.cache)
+    }
+
+    #[inline]
+    fn calc_index(&self, key: u64) -> usize {
+        (HASH_MULTIPLIER.wrapping_mul(key) & self.mod_mask) as usize
+    }
+}
+
+impl<V: Clone> CowIntMap<V> {
+    /// Insert key/value into the CowIntMap if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    pub fn insert(&mut self, key: u64, value: V) -> bool {
+        if self.contains_key(key) {
+            return false;
+        }
+
+        if ((self.count + 1) * 100) / self.cache.len() > 70 {
+            self.grow();
+        }
+
+        let ix = self.calc_index(key);
+        self.bucket_mut(ix).push((key, value));
+        self.count += 1;
+
+        true
+    }
+
+    /// Get mutable value from the CowIntMap, copying its bucket first if it is shared.
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let ix = self.calc_index(key);
+        let pos = self.cache[ix].iter().position(|kv| kv.0 == key)?;
+
+        Some(&mut self.bucket_mut(ix)[pos].1)
+    }
+
+    /// Remove value from the CowIntMap.
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let ix = self.calc_index(key);
+        let pos = self.cache[ix].iter().position(|kv| kv.0 == key)?;
+
+        self.count -= 1;
+        Some(self.bucket_mut(ix).swap_remove(pos).1)
+    }
+
+    fn bucket_mut(&mut self, ix: usize) -> &mut Vec<(u64, V)> {
+        Arc::make_mut(&mut Arc::make_mut(&mut self.cache)[ix])
+    }
+
+    fn grow(&mut self) {
+        self.size += 1;
+        let new_lim = 1usize << self.size;
+        self.mod_mask = (new_lim as u64) - 1;
+
+        let mut buckets: Vec<Vec<(u64, V)>> = Vec::with_capacity(new_lim);
+        for _ in 0..new_lim {
+            buckets.push(Vec::new());
+        }
+
+        let old = std::mem::replace(&mut self.cache, Arc::new(Vec::new()));
+        let old = Arc::try_unwrap(old).unwrap_or_else(|shared| (*shared).clone());
+        for vals in old {
+            let vals = Arc::try_unwrap(vals).unwrap_or_else(|shared| (*shared).clone());
+            for kv in vals {
+                let ix = self.calc_index(kv.0);
+                buckets[ix].push(kv);
+            }
+        }
+
+        self.cache = Arc::new(buckets.into_iter().map(Arc::new).collect());
+    }
+}
+
+impl<V> Clone for CowIntMap<V> {
+    fn clone(&self) -> Self {
+        CowIntMap {
+            cache: Arc::clone(&self.cache),
+            size: self.size,
+            mod_mask: self.mod_mask,
+            count: self.count,
+        }
+    }
+}
+
+impl<V> Default for CowIntMap<V> {
+    fn default() -> Self {
+        CowIntMap::new()
+    }
+}
+
+impl<V: Clone> From<IntMap<V>> for CowIntMap<V> {
+    fn from(map: IntMap<V>) -> Self {
+        let mut cow = CowIntMap::with_capacity(map.capacity());
+        for (k, v) in map {
+            cow.insert(k, v);
+        }
+        cow
+    }
+}
+
+impl<V> std::fmt::Debug for CowIntMap<V>
+where
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
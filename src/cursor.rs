@@ -0,0 +1,92 @@
+use crate::IntMap;
+
+/// A cursor over the entries of an `IntMap` that can remove the entry it is on.
+///
+/// This is constructed by `IntMap::cursor_mut`. `next` moves to the next entry in
+/// iteration order and `remove_current` removes the entry `next` returned last. Unlike
+/// `retain`, the caller drives the traversal, so decisions can depend on state carried
+/// between entries. Every entry is visited exactly once, whether or not entries are
+/// removed along the way.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::IntMap;
+///
+/// let mut map: IntMap<u64> = (0..10).map(|i| (i, i)).collect();
+///
+/// // Keep at most one entry for every value modulo 3.
+/// let mut seen = [false; 3];
+/// let mut cursor = map.cursor_mut();
+/// while let Some((_, v)) = cursor.next() {
+///     let rem = (*v % 3) as usize;
+///     if seen[rem] {
+///         cursor.remove_current();
+///     }
+///     seen[rem] = true;
+/// }
+///
+/// assert_eq!(map.len(), 3);
+/// ```
+pub struct CursorMut<'a, V: 'a> {
+    int_map: &'a mut IntMap<V>,
+    // Bucket of the next entry to visit.
+    bucket: usize,
+    // Position of the next entry to visit in its bucket.
+    pos: usize,
+    // True if the entry before `pos` was yielded and not removed yet.
+    has_current: bool,
+}
+
+impl<'a, V> CursorMut<'a, V> {
+    pub(crate) fn new(int_map: &'a mut IntMap<V>) -> Self {
+        CursorMut {
+            int_map,
+            bucket: 0,
+            pos: 0,
+            has_current: false,
+        }
+    }
+
+    /// Moves to the next entry and returns it, or `None` once all entries were visited.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(u64, &mut V)> {
+        while self.bucket < self.int_map.cache.len() {
+            if self.pos < self.int_map.cache[self.bucket].len() {
+                let kv = &mut self.int_map.cache[self.bucket][self.pos];
+                self.pos += 1;
+                self.has_current = true;
+                return Some((kv.0, &mut kv.1));
+            }
+
+            self.bucket += 1;
+            self.pos = 0;
+        }
+
+        self.has_current = false;
+        // The traversal is over, so a resize can no longer make it skip or repeat entries.
+        self.int_map.maybe_shrink();
+        None
+    }
+
+    /// Removes the entry last returned by `next` and returns its value.
+    ///
+    /// Returns `None` if `next` was not called yet, returned `None`, or its entry was
+    /// already removed. If automatic shrinking is enabled, the map is only shrunk once
+    /// `next` has run past the last entry, since resizing during the traversal would move
+    /// the entries under the cursor.
+    pub fn remove_current(&mut self) -> Option<V> {
+        if !self.has_current {
+            return None;
+        }
+        self.has_current = false;
+
+        // The last entry of the bucket moves into the hole, visit it next.
+        self.pos -= 1;
+        let (key, value) = self.int_map.cache[self.bucket].swap_remove(self.pos);
+        self.int_map.count -= 1;
+        self.int_map.record_remove(key);
+
+        Some(value)
+    }
+}
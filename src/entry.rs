@@ -0,0 +1,191 @@
+use crate::IntMap;
+
+/// A view into a single entry in an `IntMap`, which may either be vacant or occupied.
+///
+/// This is constructed by `IntMap::entry`.
+pub enum Entry<'a, V: 'a> {
+    Occupied(OccupiedEntry<'a, V>),
+    Vacant(VacantEntry<'a, V>),
+}
+
+impl<'a, V> Entry<'a, V> {
+    pub(crate) fn new(key: u64, int_map: &'a mut IntMap<V>) -> Self {
+        let ix = int_map.calc_index(key);
+
+        match int_map.cache[ix].iter().position(|kv| kv.0 == key) {
+            Some(vals_ix) => Entry::Occupied(OccupiedEntry {
+                ix,
+                vals_ix,
+                int_map,
+            }),
+            None => Entry::Vacant(VacantEntry { key, int_map }),
+        }
+    }
+
+    /// Returns the key of this entry.
+    pub fn key(&self) -> u64 {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting `default` if empty, and returns a
+    /// mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut counters: IntMap<u64> = IntMap::new();
+    ///
+    /// for key in [1, 2, 1].iter() {
+    ///     *counters.entry(*key).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counters.get(1), Some(&2));
+    /// assert_eq!(counters.get(2), Some(&1));
+    /// ```
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and
+    /// returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and
+    /// returns a mutable reference to the value in the entry.
+    ///
+    /// The key is passed to `default`, which only runs if the entry is vacant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut squares: IntMap<u64> = IntMap::new();
+    /// squares.insert(3, 0);
+    ///
+    /// assert_eq!(*squares.entry(4).or_insert_with_key(|k| k * k), 16);
+    /// assert_eq!(*squares.entry(3).or_insert_with_key(|_| unreachable!()), 0);
+    /// ```
+    pub fn or_insert_with_key<F: FnOnce(u64) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and
+    /// returns a mutable reference to the value in the entry.
+    ///
+    /// If `default` fails, its error is returned and the map is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    ///
+    /// let res: Result<_, ()> = map.entry(1).or_try_insert_with(|| Ok(10));
+    /// assert_eq!(res, Ok(&mut 10));
+    ///
+    /// let res = map.entry(2).or_try_insert_with(|| "2".parse::<u64>().map_err(|_| ()));
+    /// assert_eq!(res, Ok(&mut 2));
+    ///
+    /// let res = map.entry(3).or_try_insert_with(|| "three".parse::<u64>());
+    /// assert!(res.is_err());
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn or_try_insert_with<E, F: FnOnce() -> Result<V, E>>(
+        self,
+        default: F,
+    ) -> Result<&'a mut V, E> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(default()?)),
+        }
+    }
+}
+
+/// A view into an occupied entry in an `IntMap`. It is part of the `Entry` enum.
+pub struct OccupiedEntry<'a, V: 'a> {
+    // Index to IntMap::cache
+    ix: usize,
+    // Index to the bucket, guaranteed to be valid
+    vals_ix: usize,
+    int_map: &'a mut IntMap<V>,
+}
+
+impl<'a, V> OccupiedEntry<'a, V> {
+    /// Returns the key of this entry.
+    pub fn key(&self) -> u64 {
+        self.int_map.cache[self.ix][self.vals_ix].0
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        &self.int_map.cache[self.ix][self.vals_ix].1
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.int_map.cache[self.ix][self.vals_ix].1
+    }
+
+    /// Converts the entry into a mutable reference to the value in the entry with a
+    /// lifetime bound to the `IntMap` itself.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.int_map.cache[self.ix][self.vals_ix].1
+    }
+
+    /// Sets the value of the entry and returns the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        let key = self.key();
+        self.int_map.record_overwrite(key);
+
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the value out of the entry.
+    ///
+    /// Like `IntMap::remove`, this may shrink the map if automatic shrinking is enabled.
+    pub fn remove(self) -> V {
+        let (key, value) = self.int_map.cache[self.ix].swap_remove(self.vals_ix);
+        self.int_map.count -= 1;
+        self.int_map.record_remove(key);
+        self.int_map.maybe_shrink();
+        value
+    }
+}
+
+/// A view into a vacant entry in an `IntMap`. It is part of the `Entry` enum.
+pub struct VacantEntry<'a, V: 'a> {
+    key: u64,
+    int_map: &'a mut IntMap<V>,
+}
+
+impl<'a, V: 'a> VacantEntry<'a, V> {
+    /// Returns the key of this entry.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// Inserts `value` into the map and returns a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.int_map.insert_new(self.key, value)
+    }
+}
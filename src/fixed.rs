@@ -0,0 +1,194 @@
+use crate::HASH_MULTIPLIER;
+
+/// A map for `u64` keys holding at most `N` entries, which never allocates after
+/// construction.
+///
+/// All slots are allocated up front by `new`, rounded up to the next power of two so the
+/// same multiplicative hash and fast modulus as `IntMap` can be used. Entries live directly
+/// in the slots (open addressing with linear probing) instead of per-bucket vectors, so
+/// memory use is fixed. Inserting a new key into a full map returns a `CapacityError`
+/// instead of resizing.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::FixedIntMap;
+///
+/// let mut map: FixedIntMap<u64, 2> = FixedIntMap::new();
+/// assert_eq!(map.insert(1, 10), Ok(true));
+/// assert_eq!(map.insert(2, 20), Ok(true));
+///
+/// let err = map.insert(3, 30).unwrap_err();
+/// assert_eq!((err.key, err.value), (3, 30));
+/// ```
+pub struct FixedIntMap<V, const N: usize> {
+    slots: Box<[Option<(u64, V)>]>,
+    mod_mask: u64,
+    count: usize,
+}
+
+/// Error returned by `FixedIntMap::insert` when the map is full, handing back the entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapacityError<V> {
+    pub key: u64,
+    pub value: V,
+}
+
+impl<V> std::fmt::Display for CapacityError<V> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "map is full, cannot insert key {}", self.key)
+    }
+}
+
+impl<V: std::fmt::Debug> std::error::Error for CapacityError<V> {}
+
+impl<V, const N: usize> FixedIntMap<V, N> {
+    /// Creates a new FixedIntMap, allocating room for `N` entries.
+    pub fn new() -> Self {
+        let lim = N.max(1).next_power_of_two();
+        let mut slots = Vec::with_capacity(lim);
+        for _ in 0..lim {
+            slots.push(None);
+        }
+
+        FixedIntMap {
+            slots: slots.into_boxed_slice(),
+            mod_mask: (lim as u64) - 1,
+            count: 0,
+        }
+    }
+
+    /// Insert key/value into the FixedIntMap if the key is not yet inserted.
+    ///
+    /// Returns `Ok(true)` if key/value were inserted, `Ok(false)` if the key was already
+    /// present and an error if the key is new but the map already holds `N` entries.
+    pub fn insert(&mut self, key: u64, value: V) -> Result<bool, CapacityError<V>> {
+        if self.find(key).is_some() {
+            return Ok(false);
+        }
+
+        if self.count == N {
+            return Err(CapacityError { key, value });
+        }
+
+        // There is at least one free slot since count < N <= slots.len().
+        let mut ix = self.calc_index(key);
+        while self.slots[ix].is_some() {
+            ix = self.next_index(ix);
+        }
+
+        self.slots[ix] = Some((key, value));
+        self.count += 1;
+
+        Ok(true)
+    }
+
+    /// Get value from the FixedIntMap.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let ix = self.find(key)?;
+        self.slots[ix].as_ref().map(|kv| &kv.1)
+    }
+
+    /// Get mutable value from the FixedIntMap.
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let ix = self.find(key)?;
+        self.slots[ix].as_mut().map(|kv| &mut kv.1)
+    }
+
+    /// Returns true if key is in map.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Remove value from the FixedIntMap.
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let mut hole = self.find(key)?;
+        let removed = self.slots[hole].take().map(|kv| kv.1);
+        self.count -= 1;
+
+        // Shift back following entries that would otherwise become unreachable.
+        let mut ix = self.next_index(hole);
+        while let Some(k) = self.slots[ix].as_ref().map(|kv| kv.0) {
+            let ideal = self.calc_index(k);
+            let dist_ideal = ix.wrapping_sub(ideal) & self.mod_mask as usize;
+            let dist_hole = ix.wrapping_sub(hole) & self.mod_mask as usize;
+            if dist_ideal >= dist_hole {
+                self.slots[hole] = self.slots[ix].take();
+                hole = ix;
+            }
+            ix = self.next_index(ix);
+        }
+
+        removed
+    }
+
+    /// Removes all elements from map.
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+
+        self.count = 0;
+    }
+
+    /// Number of elements in map.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Maximum number of elements the map can hold, `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &V)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|kv| (kv.0, &kv.1)))
+    }
+
+    fn find(&self, key: u64) -> Option<usize> {
+        let mut ix = self.calc_index(key);
+
+        for _ in 0..self.slots.len() {
+            match self.slots[ix] {
+                Some((k, _)) if k == key => return Some(ix),
+                Some(_) => ix = self.next_index(ix),
+                None => return None,
+            }
+        }
+
+        None
+    }
+
+    #[inline]
+    fn calc_index(&self, key: u64) -> usize {
+        (HASH_MULTIPLIER.wrapping_mul(key) & self.mod_mask) as usize
+    }
+
+    #[inline]
+    fn next_index(&self, ix: usize) -> usize {
+        (ix + 1) & self.mod_mask as usize
+    }
+}
+
+impl<V, const N: usize> Default for FixedIntMap<V, N> {
+    fn default() -> Self {
+        FixedIntMap::new()
+    }
+}
+
+impl<V, const N: usize> std::fmt::Debug for FixedIntMap<V, N>
+where
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
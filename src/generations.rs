@@ -0,0 +1,45 @@
+use crate::StableHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+// Per-entry generations, see `IntMap::generation_of`.
+#[derive(Clone, Default)]
+pub(crate) struct EntryGenerations {
+    // Generation of every key in the map. Integer keys need no SipHash, the cheap
+    // `StableHasher` keeps the extra lookup on every insert small.
+    current: HashMap<u64, u32, BuildHasherDefault<StableHasher>>,
+    // Highest generation a removed key had. Keys inserted later start above it, so a key
+    // inserted again never gets a generation it had before.
+    floor: u32,
+}
+
+impl EntryGenerations {
+    // Generation of a key in the map. A key can only be missing if a `drain` was dropped
+    // before it got to the key, the floor is then above any generation it was handed out.
+    #[inline]
+    pub(crate) fn get(&self, key: u64) -> u32 {
+        self.current.get(&key).copied().unwrap_or(self.floor)
+    }
+
+    #[inline]
+    pub(crate) fn bump(&mut self, key: u64) -> u32 {
+        let generation = self.current.entry(key).or_insert(self.floor);
+        *generation = generation.wrapping_add(1);
+        *generation
+    }
+
+    #[inline]
+    pub(crate) fn remove(&mut self, key: u64) {
+        if let Some(generation) = self.current.remove(&key) {
+            self.floor = self.floor.max(generation);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.floor = self
+            .current
+            .values()
+            .fold(self.floor, |floor, g| floor.max(*g));
+        self.current.clear();
+    }
+}
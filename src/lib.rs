@@ -1,13 +1,94 @@
 extern crate core;
 
+mod arena;
+#[cfg(feature = "binary-io")]
+mod binary;
+mod cow;
+mod cursor;
+mod entry;
+mod fixed;
+#[cfg(feature = "entry-generations")]
+mod generations;
+#[cfg(feature = "rayon")]
+mod par;
+mod perfect;
+#[cfg(feature = "track-removals")]
+mod removals;
+#[cfg(feature = "serde")]
+mod serialize;
+mod set;
+#[cfg(feature = "stats")]
+mod stats;
+mod tx;
+
 use core::iter::{IntoIterator, Iterator};
 
+pub use arena::ArenaIntMap;
+#[cfg(feature = "binary-io")]
+pub use binary::BinaryValue;
+pub use cow::CowIntMap;
+pub use cursor::CursorMut;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use fixed::{CapacityError, FixedIntMap};
+#[cfg(feature = "rayon")]
+pub use par::ParValuesMut;
+pub use perfect::{PerfectHashError, PerfectIntMap};
+pub use set::IntSet;
+#[cfg(feature = "stats")]
+pub use stats::MapStats;
+pub use tx::TxGuard;
+
+// Number of slots a map created by `IntMap::new` starts with, must be a power of two.
+const DEFAULT_CAPACITY: usize = 4;
+
+// Load rate in percent above which maps grow, unless constructed with another one.
+const DEFAULT_MAX_LOAD: usize = 70;
+
+// 2^64 divided by the golden ratio, keys are multiplied with it to get their hash.
+const HASH_MULTIPLIER: u64 = 11400714819323198549;
+
+// Seed of maps created by `IntMap::deterministic`, part of its documented behavior.
+const DETERMINISTIC_SEED: u64 = 0;
+
+/// A hash map specialized for `u64` keys.
+///
+/// # Thread safety
+///
+/// `IntMap<V>` is `Send` when `V: Send` and `Sync` when `V: Sync`. All methods taking
+/// `&self` are reads, so a shared `&IntMap<V>` can be read from several threads at once.
+/// The only interior mutability are the counters of the `stats` feature, which lookups
+/// bump with relaxed atomic adds. Mutation requires `&mut self` and thus exclusive
+/// access, as usual.
 #[derive(Clone)]
 pub struct IntMap<V> {
     cache: Vec<Vec<(u64, V)>>,
     size: u32,
     mod_mask: u64,
     count: usize,
+    seed: u64,
+    bucket_capacity: usize,
+    frozen: bool,
+    auto_shrink: bool,
+    // Size the shrinking paths never go below.
+    min_size: u32,
+    // Positions freed by `remove_keep_positions` as sorted (bucket, holes) pairs, holes
+    // sorted too. `BucketSlot` indices count these positions, so handles to later entries
+    // keep pointing at them. Empty, and thus unallocated, unless that method is used.
+    slot_holes: Vec<(usize, Vec<usize>)>,
+    // Load rate in percent above which the map grows.
+    max_load: usize,
+    on_resize: ResizeHook,
+    generation: u64,
+    // Generation at which each key was inserted. Removed keys may linger, which is
+    // harmless since only keys still in the map are looked up.
+    #[cfg(feature = "change-tracking")]
+    inserted_at: std::collections::HashMap<u64, u64>,
+    #[cfg(feature = "entry-generations")]
+    entry_generations: generations::EntryGenerations,
+    #[cfg(feature = "track-removals")]
+    removals: removals::RemovalLog,
+    #[cfg(feature = "stats")]
+    stats: stats::StatsCounters,
 }
 
 impl<V> IntMap<V> {
@@ -21,11 +102,43 @@ impl<V> IntMap<V> {
     /// let mut map: IntMap<u64> = IntMap::new();
     /// ```
     pub fn new() -> Self {
-        IntMap::with_capacity(4)
+        IntMap::with_size(DEFAULT_CAPACITY.trailing_zeros())
+    }
+
+    /// Creates a new IntMap whose hash seed is pinned to 0, i.e. the plain multiplicative
+    /// hash.
+    ///
+    /// Keys are placed in the same buckets, and thus iterated in the same order, whenever
+    /// the same operations are applied, across runs and crate versions, even if the
+    /// default seed of `new` changes. Use this for benchmarks and snapshot tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut a: IntMap<u64> = IntMap::deterministic();
+    /// let mut b: IntMap<u64> = IntMap::deterministic();
+    /// for i in 0..100 {
+    ///     a.insert(i * 7919, i);
+    ///     b.insert(i * 7919, i);
+    /// }
+    ///
+    /// assert_eq!(a.seed(), 0);
+    /// assert!(a.iter().eq(b.iter()));
+    /// ```
+    pub fn deterministic() -> Self {
+        let mut map = IntMap::new();
+        if map.seed != DETERMINISTIC_SEED {
+            map.reseed(DETERMINISTIC_SEED);
+        }
+        map
     }
 
-    /// Creates a new IntMap with at least the given capacity, rounded
-    /// to the next power of two.
+    /// Creates a new IntMap that can hold at least `capacity` elements without resizing.
+    ///
+    /// The number of slots is `capacity` divided by the maximum load rate of 70%, rounded
+    /// up to the next power of two.
     ///
     /// # Examples
     ///
@@ -33,66 +146,333 @@ impl<V> IntMap<V> {
     /// use intmap::IntMap;
     ///
     /// let mut map: IntMap<u64> = IntMap::with_capacity(20);
+    /// assert_eq!(map.capacity(), 32);
     /// ```
     pub fn with_capacity(capacity: usize) -> Self {
+        IntMap::with_size(IntMap::<V>::min_size_for(capacity, DEFAULT_MAX_LOAD))
+    }
+
+    /// Creates a new IntMap that holds `expected` elements at a load rate of at most
+    /// `max_load_percent`, and keeps growing at that load rate instead of the default 70%.
+    ///
+    /// Lower load rates make lookups faster at the cost of memory, higher ones the other
+    /// way round.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_load_percent` is not within 1 to 100.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_capacity_and_load(100, 25);
+    /// for i in 0..100 {
+    ///     map.insert(i, i);
+    /// }
+    /// assert_eq!(map.capacity(), 512);
+    /// assert!(map.load_rate() <= 25.0);
+    /// ```
+    pub fn with_capacity_and_load(expected: usize, max_load_percent: u8) -> Self {
+        assert!(
+            (1..=100).contains(&max_load_percent),
+            "max load {}% out of range 1..=100",
+            max_load_percent
+        );
+
+        let max_load = max_load_percent as usize;
+        let mut map = IntMap::with_size(IntMap::<V>::min_size_for(expected, max_load));
+        map.max_load = max_load;
+        map
+    }
+
+    /// Creates a new IntMap with at least `min_capacity` slots, rounded to the next power of
+    /// two, that it never shrinks below.
+    ///
+    /// `clear_and_shrink`, automatic shrinking and `resize_to_size` all respect this floor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_min_capacity(256);
+    /// for i in 0..1_000 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// map.clear_and_shrink();
+    /// assert_eq!(map.capacity(), 256);
+    /// ```
+    pub fn with_min_capacity(min_capacity: usize) -> Self {
+        let min_size = min_capacity
+            .next_power_of_two()
+            .trailing_zeros()
+            .max(DEFAULT_CAPACITY.trailing_zeros());
+
+        let mut map = IntMap::with_size(min_size);
+        map.min_size = min_size;
+        map
+    }
+
+    /// Creates a new IntMap that remembers the last `history` removed keys instead of the
+    /// default 16, see `recent_removals`. A history of 0 disables tracking.
+    ///
+    /// Requires the `track-removals` feature.
+    #[cfg(feature = "track-removals")]
+    pub fn with_removal_history(history: usize) -> Self {
+        let mut map = IntMap::new();
+        map.removals = removals::RemovalLog::with_capacity(history);
+        map
+    }
+
+    fn with_size(size: u32) -> Self {
         let mut map = IntMap {
             cache: Vec::new(),
             size: 0,
             count: 0,
             mod_mask: 0,
+            seed: 0,
+            bucket_capacity: 0,
+            frozen: false,
+            auto_shrink: false,
+            min_size: DEFAULT_CAPACITY.trailing_zeros(),
+            max_load: DEFAULT_MAX_LOAD,
+            slot_holes: Vec::new(),
+            on_resize: ResizeHook(None),
+            generation: 0,
+            #[cfg(feature = "change-tracking")]
+            inserted_at: Default::default(),
+            #[cfg(feature = "entry-generations")]
+            entry_generations: Default::default(),
+            #[cfg(feature = "track-removals")]
+            removals: Default::default(),
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
         };
 
-        map.increase_cache();
+        map.rehash_to_size(size);
+
+        // Building the initial table does not count as a resize.
+        #[cfg(feature = "stats")]
+        {
+            map.stats = Default::default();
+        }
+
+        map
+    }
+
+    /// Builds a map from `iter`, failing if some bucket ends up with more than `max_chain`
+    /// entries.
+    ///
+    /// This guards against input crafted to collide, such as keys sharing their low bits.
+    /// If the default hash gives a bucket that is too long, the map tries other seeds as
+    /// with `optimize_for` and only fails if none of them is good enough.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map = IntMap::from_iter_checked((0..1_000).map(|i| (i, i)), 8).unwrap();
+    /// assert_eq!(map.len(), 1_000);
+    ///
+    /// // Keys sharing their low bits land in one bucket by default, a seed spreads them out.
+    /// let clustered = (0..1_000).map(|i| (i << 20, i));
+    /// let map = IntMap::from_iter_checked(clustered, 16).unwrap();
+    /// assert!(map.max_bucket_len() <= 16);
+    ///
+    /// let err = IntMap::from_iter_checked(vec![(1, 1)], 0).unwrap_err();
+    /// assert_eq!(err.max_bucket_len, 1);
+    /// ```
+    pub fn from_iter_checked<I: IntoIterator<Item = (u64, V)>>(
+        iter: I,
+        max_chain: usize,
+    ) -> Result<Self, TooClustered> {
+        let mut map: IntMap<V> = iter.into_iter().collect();
+
+        if map.max_bucket_len() > max_chain {
+            let keys: Vec<u64> = map.keys().copied().collect();
+            map.optimize_for(&keys);
+        }
+
+        let max_bucket_len = map.max_bucket_len();
+        if max_bucket_len > max_chain {
+            return Err(TooClustered {
+                max_bucket_len,
+                max_chain,
+            });
+        }
+
+        Ok(map)
+    }
+
+    /// Builds a map from parallel columns of keys and values, pairing up `keys[i]` with
+    /// `values[i]`.
+    ///
+    /// Fails if the columns have different lengths. As with `insert`, the first value for
+    /// a duplicated key wins and later ones are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map = IntMap::from_columns(&[1, 2, 1], vec!["a", "b", "c"]).unwrap();
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.get(1), Some(&"a"));
+    ///
+    /// let err = IntMap::from_columns(&[1, 2], vec!["a"]).unwrap_err();
+    /// assert_eq!((err.keys, err.values), (2, 1));
+    /// ```
+    pub fn from_columns(keys: &[u64], values: Vec<V>) -> Result<Self, LengthMismatch> {
+        if keys.len() != values.len() {
+            return Err(LengthMismatch {
+                keys: keys.len(),
+                values: values.len(),
+            });
+        }
 
-        while map.lim() < capacity {
-            map.increase_cache();
+        let mut map = IntMap::with_capacity(keys.len());
+        for (k, v) in keys.iter().zip(values) {
+            map.insert(*k, v);
         }
 
+        Ok(map)
+    }
+
+    /// Creates a new, empty IntMap with the same capacity and hashing as `other`, so the
+    /// same keys end up in the same buckets in both maps. The maximum load rate, minimum
+    /// capacity and bucket capacity are taken over as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let other: IntMap<u64> = IntMap::with_capacity(1_000);
+    /// let map: IntMap<String> = IntMap::with_shape_of(&other);
+    /// assert_eq!(map.capacity(), other.capacity());
+    /// ```
+    pub fn with_shape_of<W>(other: &IntMap<W>) -> Self {
+        let mut map = IntMap::with_size(other.size);
+        // The buckets are still empty, so they need not be rehashed for the seed.
+        map.seed = other.seed;
+        map.max_load = other.max_load;
+        map.min_size = other.min_size;
+        map.bucket_capacity = other.bucket_capacity;
+        map.preallocate_buckets(other.bucket_capacity);
         map
     }
 
     /// Ensures that the IntMap has space for at least `additional` more elements
+    /// without exceeding its maximum load rate, so inserting them does not resize.
     pub fn reserve(&mut self, additional: usize) {
-        let capacity = (self.count + additional).next_power_of_two();
-        while self.lim() < capacity {
-            self.increase_cache();
+        let size = Self::min_size_for(self.count + additional, self.max_load);
+        if size > self.size {
+            self.rehash_to_size(size);
         }
     }
 
-    /// Insert key/value into the IntMap if the key is not yet inserted.
+    /// Extends the map with the entries of `iter`, reserving room for `expected` more
+    /// elements up front.
     ///
-    /// This function returns true if key/value were inserted and false otherwise.
+    /// Useful for iterators with a poor `size_hint`. `expected` is only a hint, every
+    /// entry is inserted no matter how many there are.
     ///
     /// # Examples
     ///
     /// ```
     /// use intmap::IntMap;
     ///
-    /// let mut map = IntMap::new();
-    /// assert!(map.insert(21, "Eat my shorts"));
-    /// assert!(!map.insert(21, "Ay, caramba"));
-    /// assert_eq!(map.get(21), Some(&"Eat my shorts"));
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.extend_with_hint((0..100).filter(|i| i % 2 == 0).map(|i| (i, i)), 50);
+    /// assert_eq!(map.len(), 50);
     /// ```
-    pub fn insert(&mut self, key: u64, value: V) -> bool {
-        let ix = self.calc_index(key);
+    pub fn extend_with_hint<I: IntoIterator<Item = (u64, V)>>(&mut self, iter: I, expected: usize) {
+        self.reserve(expected);
 
-        let ref mut vals = self.cache[ix];
-        for ref kv in vals.iter() {
-            if kv.0 == key {
-                return false;
-            }
+        for elem in iter {
+            self.insert(elem.0, elem.1);
         }
+    }
 
-        self.count += 1;
-        vals.push((key, value));
-        if (self.count & 4) == 4 {
-            self.ensure_load_rate();
+    /// Inserts every pair of `pairs`, overwriting existing values, so each key ends up with
+    /// the value of its last occurrence in the slice.
+    ///
+    /// This matches collecting into a std `HashMap`, unlike `insert` and `extend`, which
+    /// keep the first value for a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<&str> = IntMap::new();
+    /// map.insert_slice_last_wins(&[(1, "a"), (2, "b"), (1, "c")]);
+    ///
+    /// assert_eq!(map.get(1), Some(&"c"));
+    /// assert_eq!(map.get(2), Some(&"b"));
+    /// ```
+    pub fn insert_slice_last_wins(&mut self, pairs: &[(u64, V)])
+    where
+        V: Clone,
+    {
+        self.reserve(pairs.len());
+
+        for (k, v) in pairs {
+            self.insert_mut(*k, v.clone());
         }
+    }
 
-        true
+    /// Sets how many entries every bucket preallocates room for whenever the map resizes.
+    ///
+    /// By default buckets start out unallocated and the first insert into a bucket allocates
+    /// room for a few entries. Preallocating does not reduce the number of allocations, empty
+    /// buckets get one too, but it moves them from `insert` into the resize, so inserts
+    /// between resizes never allocate as long as no bucket outgrows its reserved room.
+    /// Existing buckets are left alone until the next resize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.set_bucket_capacity(1);
+    /// map.reserve(1_000);
+    /// ```
+    pub fn set_bucket_capacity(&mut self, bucket_capacity: usize) {
+        self.bucket_capacity = bucket_capacity;
     }
 
-    /// Get value from the IntMap.
+    /// Makes every existing bucket reserve room for at least `per_bucket` entries now.
+    ///
+    /// This moves the allocations of first inserts into buckets to a warm-up phase. Unlike
+    /// `set_bucket_capacity` it only affects the current buckets, the next resize
+    /// allocates new ones as configured there. Entries are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_capacity(1_000);
+    /// map.preallocate_buckets(2);
+    /// assert!(map.raw_buckets().iter().all(|vals| vals.capacity() >= 2));
+    /// ```
+    pub fn preallocate_buckets(&mut self, per_bucket: usize) {
+        for vals in self.cache.iter_mut() {
+            vals.reserve(per_bucket.saturating_sub(vals.len()));
+        }
+    }
+
+    /// Resizes the map to exactly `2^size` slots and rehashes all entries.
+    ///
+    /// The map is left untouched and false is returned if the requested size would put
+    /// the map above its maximum load rate or below its floor, see `with_min_capacity`, or
+    /// if the bucket table for it cannot be allocated.
     ///
     /// # Examples
     ///
@@ -101,30 +481,46 @@ impl<V> IntMap<V> {
     ///
     /// let mut map: IntMap<u64> = IntMap::new();
     /// map.insert(21, 42);
-    /// let val = map.get(21);
-    /// assert!(val.is_some());
-    /// assert_eq!(*val.unwrap(), 42);
-    /// assert!(map.contains_key(21));
+    ///
+    /// assert!(map.resize_to_size(10));
+    /// assert_eq!(map.capacity(), 1024);
+    /// assert_eq!(map.get(21), Some(&42));
+    ///
+    /// for i in 0..100 {
+    ///     map.insert(i, i);
+    /// }
+    /// assert!(!map.resize_to_size(2));
+    /// assert_eq!(map.capacity(), 1024);
     /// ```
-    pub fn get(&self, key: u64) -> Option<&V> {
-        let ix = self.calc_index(key);
+    pub fn resize_to_size(&mut self, size: u32) -> bool {
+        let lim = match 1usize.checked_shl(size) {
+            Some(lim) => lim,
+            None => return false,
+        };
 
-        let ref vals = self.cache[ix];
+        if !self.within_load_rate(lim) || size < self.min_size {
+            return false;
+        }
 
-        if vals.len() > 0 {
-            for kv in vals.iter() {
-                if kv.0 == key {
-                    return Some(&kv.1);
-                }
+        if size != self.size {
+            // Refuse a table that cannot be allocated instead of aborting while rehashing.
+            // `rehash_to_size` refills the emptied table, so it keeps this capacity.
+            let additional = lim.saturating_sub(self.cache.len());
+            if self.cache.try_reserve_exact(additional).is_err() {
+                return false;
             }
 
-            return None;
-        } else {
-            return None;
+            self.rehash_to_size(size);
         }
+
+        true
     }
 
-    /// Get mutable value from the IntMap.
+    /// Resizes the map for the given keys and inserts `V::default()` for every one of them
+    /// that is not yet present.
+    ///
+    /// The map is sized once so all keys fit within the maximum load rate, so neither this
+    /// nor filling in the values afterwards with `get_mut` or `insert_mut` resizes it.
     ///
     /// # Examples
     ///
@@ -132,36 +528,2893 @@ impl<V> IntMap<V> {
     /// use intmap::IntMap;
     ///
     /// let mut map: IntMap<u64> = IntMap::new();
-    /// map.insert(21, 42);
+    /// map.reserve_for_keys(vec![1, 2, 3]);
+    /// assert_eq!(map.get(2), Some(&0));
     ///
-    /// assert_eq!(*map.get(21).unwrap(), 42);
-    /// assert!(map.contains_key(21));
+    /// *map.get_mut(2).unwrap() = 20;
+    /// assert_eq!(map.get(2), Some(&20));
+    /// ```
+    pub fn reserve_for_keys<I: IntoIterator<Item = u64>>(&mut self, keys: I)
+    where
+        V: Default,
+    {
+        let mut keys: Vec<u64> = keys
+            .into_iter()
+            .filter(|k| !self.contains_key(*k))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let size = IntMap::<V>::min_size_for(self.count + keys.len(), self.max_load);
+        if size > self.size {
+            self.rehash_to_size(size);
+        }
+
+        self.count += keys.len();
+        for key in keys {
+            let ix = self.calc_index(key);
+            self.cache[ix].push((key, V::default()));
+            self.record_insert(key);
+        }
+    }
+
+    /// Stops inserts from growing the map, so no insert pays for a resize.
     ///
-    /// {
-    ///     let mut val = map.get_mut(21).unwrap();
-    ///     *val+=1;
+    /// Inserts past the maximum load rate keep working, but buckets get longer and lookups
+    /// slower. Explicit resizes through `reserve` or `resize_to_size` are still performed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_capacity(64);
+    /// let capacity = map.capacity();
+    /// map.freeze_capacity();
+    ///
+    /// for i in 0..1_000 {
+    ///     map.insert(i, i);
     /// }
-    ///     assert_eq!(*map.get(21).unwrap(), 43);
+    /// assert_eq!(map.capacity(), capacity);
     /// ```
-    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
-        let ix = self.calc_index(key);
+    pub fn freeze_capacity(&mut self) {
+        self.frozen = true;
+    }
 
-        let ref mut vals = self.cache[ix];
+    /// Lets inserts grow the map again after `freeze_capacity`, growing it right away if
+    /// it is above its maximum load rate.
+    pub fn unfreeze_capacity(&mut self) {
+        self.frozen = false;
+        self.ensure_load_rate();
+    }
 
-        if vals.len() > 0 {
-            for kv in vals {
-                if kv.0 == key {
-                    return Some(&mut kv.1);
-                }
-            }
+    /// Registers a callback run with `(old_capacity, new_capacity)` whenever the map is
+    /// resized, replacing any previous one.
+    ///
+    /// It runs after the resize has completed, for growth as well as shrinking, but not
+    /// for rebuilds that keep the capacity such as `reseed`. Being `'static`, the callback
+    /// cannot borrow the map itself. It must be `Send` and `Sync` so the map stays so.
+    /// Clones of the map do not inherit the callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let resizes = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&resizes);
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.on_resize(move |old, new| log.lock().unwrap().push((old, new)));
+    /// map.reserve(100);
+    ///
+    /// assert_eq!(*resizes.lock().unwrap(), [(4, 256)]);
+    /// ```
+    pub fn on_resize<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, usize) + Send + Sync + 'static,
+    {
+        self.on_resize = ResizeHook(Some(Box::new(f)));
+    }
 
-            return None;
-        } else {
-            return None;
-        }
+    /// Enables or disables shrinking the map when removing entries drops its load rate
+    /// below 15%.
+    ///
+    /// The map is then halved until its load rate is back between 15% and 30%, but never
+    /// below the capacity of `IntMap::new` or the floor set by `with_min_capacity`. Since
+    /// that is well below the 70% at which the map grows, alternating inserts and removes
+    /// near either threshold do not make it resize back and forth. The check runs after
+    /// `remove` and the methods built on it, `retain`, `filter_collect_remove`,
+    /// `OccupiedEntry::remove` and `TxGuard` rollbacks, and when a `CursorMut` runs past the
+    /// last entry. `remove_keep_positions` never shrinks the map, to keep its position
+    /// guarantee, and `clear` and `drain` keep the capacity. Disabled by default, and
+    /// paused by `freeze_capacity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+    /// map.set_auto_shrink(true);
+    ///
+    /// map.retain(|k, _| k < 10);
+    /// assert!(map.capacity() <= 64);
+    /// ```
+    pub fn set_auto_shrink(&mut self, auto_shrink: bool) {
+        self.auto_shrink = auto_shrink;
+    }
+
+    /// Insert key/value into the IntMap if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map = IntMap::new();
+    /// assert!(map.insert(21, "Eat my shorts"));
+    /// assert!(!map.insert(21, "Ay, caramba"));
+    /// assert_eq!(map.get(21), Some(&"Eat my shorts"));
+    /// ```
+    pub fn insert(&mut self, key: u64, value: V) -> bool {
+        let ix = self.calc_index(key);
+
+        let ref mut vals = self.cache[ix];
+        for ref kv in vals.iter() {
+            if kv.0 == key {
+                return false;
+            }
+        }
+
+        self.count += 1;
+        vals.push((key, value));
+        self.record_insert(key);
+        if (self.count & 4) == 4 {
+            self.ensure_load_rate();
+        }
+
+        true
+    }
+
+    /// Insert key/value into the IntMap and return a mutable reference to the stored value.
+    ///
+    /// Unlike `insert`, an existing value for the key is overwritten, so the returned
+    /// reference always points to `value`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<Vec<u64>> = IntMap::new();
+    /// map.insert_mut(21, Vec::new()).push(42);
+    /// assert_eq!(map.get(21), Some(&vec![42]));
+    ///
+    /// map.insert_mut(21, vec![1]).push(2);
+    /// assert_eq!(map.get(21), Some(&vec![1, 2]));
+    /// ```
+    pub fn insert_mut(&mut self, key: u64, value: V) -> &mut V {
+        let ix = self.calc_index(key);
+
+        if let Some(pos) = self.cache[ix].iter().position(|kv| kv.0 == key) {
+            self.record_overwrite(key);
+            let kv = &mut self.cache[ix][pos];
+            kv.1 = value;
+            return &mut kv.1;
+        }
+
+        self.insert_new(key, value)
+    }
+
+    /// Insert key/value into the IntMap, overwriting and returning any previous value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::{InsertOutcome, IntMap};
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// assert_eq!(map.insert_counting(21, 42), InsertOutcome::Inserted);
+    /// assert_eq!(map.insert_counting(21, 43), InsertOutcome::Replaced(42));
+    /// assert_eq!(map.get(21), Some(&43));
+    /// ```
+    pub fn insert_counting(&mut self, key: u64, value: V) -> InsertOutcome<V> {
+        let ix = self.calc_index(key);
+
+        if let Some(pos) = self.cache[ix].iter().position(|kv| kv.0 == key) {
+            self.record_overwrite(key);
+            let old = std::mem::replace(&mut self.cache[ix][pos].1, value);
+            return InsertOutcome::Replaced(old);
+        }
+
+        self.insert_new(key, value);
+        InsertOutcome::Inserted
+    }
+
+    /// Insert `default` if the key is absent, otherwise apply `update` to the existing
+    /// value. `update` only runs if the key is present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut counters: IntMap<u64> = IntMap::new();
+    /// counters.insert_or_update(21, 1, |v| *v += 1);
+    /// counters.insert_or_update(21, 1, |v| *v += 1);
+    /// assert_eq!(counters.get(21), Some(&2));
+    /// ```
+    pub fn insert_or_update<F: FnOnce(&mut V)>(&mut self, key: u64, default: V, update: F) {
+        let ix = self.calc_index(key);
+
+        match self.cache[ix].iter().position(|kv| kv.0 == key) {
+            Some(pos) => update(&mut self.cache[ix][pos].1),
+            None => {
+                self.insert_new(key, default);
+            }
+        }
+    }
+
+    /// Get mutable value from the IntMap, inserting the result of `f` if the key is absent.
+    ///
+    /// This is `entry(key).or_insert_with(f)` without building an `Entry` first. Prefer
+    /// it in hot loops that only ever want the value; use `entry` when the occupied and
+    /// vacant cases need different handling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<Vec<u64>> = IntMap::new();
+    /// map.get_mut_or_else_insert_with(21, Vec::new).push(1);
+    /// map.get_mut_or_else_insert_with(21, || unreachable!()).push(2);
+    /// assert_eq!(map.get(21), Some(&vec![1, 2]));
+    /// ```
+    pub fn get_mut_or_else_insert_with<F: FnOnce() -> V>(&mut self, key: u64, f: F) -> &mut V {
+        let ix = self.calc_index(key);
+
+        match self.cache[ix].iter().position(|kv| kv.0 == key) {
+            Some(pos) => &mut self.cache[ix][pos].1,
+            None => self.insert_new(key, f()),
+        }
+    }
+
+    /// Get mutable value from the IntMap, inserting the result of `f` if the key is absent.
+    ///
+    /// Unlike `entry`, `f` gets read access to the whole map, as it runs before the new
+    /// entry is inserted. It only runs if the key is absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut degrees: IntMap<u64> = IntMap::new();
+    /// degrees.insert(1, 3);
+    /// degrees.insert(2, 5);
+    ///
+    /// // A new node starts at the sum of the existing degrees.
+    /// let sum = degrees.compute_if_absent(3, |map| map.values().sum());
+    /// assert_eq!(*sum, 8);
+    /// ```
+    pub fn compute_if_absent<F: FnOnce(&IntMap<V>) -> V>(&mut self, key: u64, f: F) -> &mut V {
+        let ix = self.calc_index(key);
+
+        match self.cache[ix].iter().position(|kv| kv.0 == key) {
+            Some(pos) => &mut self.cache[ix][pos].1,
+            None => {
+                let value = f(self);
+                self.insert_new(key, value)
+            }
+        }
+    }
+
+    /// Get mutable value from the IntMap, inserting the result of `f` if the key is absent.
+    ///
+    /// The returned bool is true if a new entry was created. `f` only runs in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    ///
+    /// let (val, inserted) = map.get_or_insert_status(21, || 42);
+    /// assert_eq!((*val, inserted), (42, true));
+    ///
+    /// let (val, inserted) = map.get_or_insert_status(21, || 0);
+    /// assert_eq!((*val, inserted), (42, false));
+    /// ```
+    pub fn get_or_insert_status<F: FnOnce() -> V>(&mut self, key: u64, f: F) -> (&mut V, bool) {
+        let ix = self.calc_index(key);
+
+        if let Some(pos) = self.cache[ix].iter().position(|kv| kv.0 == key) {
+            return (&mut self.cache[ix][pos].1, false);
+        }
+
+        (self.insert_new(key, f()), true)
+    }
+
+    /// Get value from the IntMap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// let val = map.get(21);
+    /// assert!(val.is_some());
+    /// assert_eq!(*val.unwrap(), 42);
+    /// assert!(map.contains_key(21));
+    /// ```
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let ix = self.calc_index(key);
+
+        let vals = &self.cache[ix];
+
+        // Counting comparisons needs the position, so one scan serves both.
+        #[cfg(feature = "stats")]
+        {
+            let pos = vals.iter().position(|kv| kv.0 == key);
+            self.stats.record_get(pos.map_or(vals.len(), |pos| pos + 1));
+            pos.map(|pos| &vals[pos].1)
+        }
+
+        // Single entry buckets are the common case at low load, compare directly.
+        #[cfg(not(feature = "stats"))]
+        match vals.len() {
+            0 => None,
+            1 if vals[0].0 == key => Some(&vals[0].1),
+            1 => None,
+            _ => vals.iter().find(|kv| kv.0 == key).map(|kv| &kv.1),
+        }
+    }
+
+    /// Get mutable value from the IntMap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// assert_eq!(*map.get(21).unwrap(), 42);
+    /// assert!(map.contains_key(21));
+    ///
+    /// {
+    ///     let mut val = map.get_mut(21).unwrap();
+    ///     *val+=1;
+    /// }
+    ///     assert_eq!(*map.get(21).unwrap(), 43);
+    /// ```
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let ix = self.calc_index(key);
+
+        let ref mut vals = self.cache[ix];
+
+        if vals.len() > 0 {
+            for kv in vals {
+                if kv.0 == key {
+                    return Some(&mut kv.1);
+                }
+            }
+
+            return None;
+        } else {
+            return None;
+        }
+    }
+
+    /// Get mutable value from the IntMap together with a `BucketSlot` handle to it.
+    ///
+    /// The handle can be passed to `get_by_slot` and `get_mut_by_slot` later to skip hashing
+    /// and, while the entry has not moved, the bucket scan. Removing other keys of the same
+    /// bucket through `remove_keep_positions` leaves the entry in place. Every other way of
+    /// removing, such as `remove`, entries and cursors, may move an entry within its bucket,
+    /// after which its handles are stale and their lookups fall back to scanning the bucket.
+    /// A resize moves entries between buckets and invalidates all handles. A stale handle
+    /// never yields another key's value; the lookup simply returns `None` and the key has to
+    /// be looked up again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// let (val, slot) = map.get_mut_or_slot(21);
+    /// *val.unwrap() += 1;
+    ///
+    /// let slot = slot.unwrap();
+    /// assert_eq!(map.get_by_slot(slot), Some(&43));
+    ///
+    /// assert_eq!(map.get_mut_or_slot(7), (None, None));
+    /// ```
+    pub fn get_mut_or_slot(&mut self, key: u64) -> (Option<&mut V>, Option<BucketSlot>) {
+        let ix = self.calc_index(key);
+
+        let vals = &mut self.cache[ix];
+        match vals.iter().position(|kv| kv.0 == key) {
+            Some(pos) => {
+                let slot = BucketSlot {
+                    bucket: ix,
+                    index: Self::slot_index(&self.slot_holes, ix, pos),
+                    key,
+                };
+                (Some(&mut vals[pos].1), Some(slot))
+            }
+            None => (None, None),
+        }
+    }
+
+    /// Get value from the IntMap through a previously acquired `BucketSlot`.
+    ///
+    /// Returns `None` if the handle has been invalidated, see `get_mut_or_slot`.
+    pub fn get_by_slot(&self, slot: BucketSlot) -> Option<&V> {
+        let pos = self.slot_position(slot)?;
+
+        Some(&self.cache[slot.bucket][pos].1)
+    }
+
+    /// Get mutable value from the IntMap through a previously acquired `BucketSlot`.
+    ///
+    /// Returns `None` if the handle has been invalidated, see `get_mut_or_slot`.
+    pub fn get_mut_by_slot(&mut self, slot: BucketSlot) -> Option<&mut V> {
+        let pos = self.slot_position(slot)?;
+
+        Some(&mut self.cache[slot.bucket][pos].1)
+    }
+
+    // Index of a `BucketSlot` for the entry at `pos` in bucket `ix`, counting the holes
+    // left by `remove_keep_positions` before it.
+    #[inline]
+    fn slot_index(slot_holes: &[(usize, Vec<usize>)], ix: usize, pos: usize) -> usize {
+        let mut index = pos;
+        if let Some(holes) = Self::bucket_holes(slot_holes, ix) {
+            for hole in holes {
+                if *hole <= index {
+                    index += 1;
+                }
+            }
+        }
+
+        index
+    }
+
+    #[inline]
+    fn bucket_holes(slot_holes: &[(usize, Vec<usize>)], ix: usize) -> Option<&Vec<usize>> {
+        if slot_holes.is_empty() {
+            return None;
+        }
+
+        let at = slot_holes.binary_search_by_key(&ix, |bh| bh.0).ok()?;
+        Some(&slot_holes[at].1)
+    }
+
+    #[inline]
+    fn slot_position(&self, slot: BucketSlot) -> Option<usize> {
+        let vals = self.cache.get(slot.bucket)?;
+
+        let mut pos = Some(slot.index);
+        if let Some(holes) = Self::bucket_holes(&self.slot_holes, slot.bucket) {
+            let before = holes.iter().take_while(|hole| **hole <= slot.index).count();
+            pos = if before > 0 && holes[before - 1] == slot.index {
+                // The entry at this index has been removed.
+                None
+            } else {
+                Some(slot.index - before)
+            };
+        }
+
+        match pos.and_then(|pos| vals.get(pos).map(|kv| (pos, kv))) {
+            Some((pos, kv)) if kv.0 == slot.key => Some(pos),
+            _ => {
+                #[cfg(feature = "stats")]
+                self.stats.record_slot_scan();
+
+                vals.iter().position(|kv| kv.0 == slot.key)
+            }
+        }
+    }
+
+    /// Get value from the IntMap, panicking with `msg` and the key if it is absent.
+    ///
+    /// # Examples
+    ///
+    /// ```should_panic
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// assert_eq!(*map.expect(21, "answer must be configured"), 42);
+    ///
+    /// map.expect(7, "answer must be configured"); // panics
+    /// ```
+    #[track_caller]
+    pub fn expect(&self, key: u64, msg: &str) -> &V {
+        match self.get(key) {
+            Some(val) => val,
+            None => panic!("{}: key {} not found", msg, key),
+        }
+    }
+
+    /// Get mutable value from the IntMap, panicking with `msg` and the key if it is absent.
+    ///
+    /// # Examples
+    ///
+    /// ```should_panic
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// *map.expect_mut(21, "answer must be configured") += 1;
+    /// assert_eq!(map.get(21), Some(&43));
+    ///
+    /// map.expect_mut(7, "answer must be configured"); // panics
+    /// ```
+    #[track_caller]
+    pub fn expect_mut(&mut self, key: u64, msg: &str) -> &mut V {
+        match self.get_mut(key) {
+            Some(val) => val,
+            None => panic!("{}: key {} not found", msg, key),
+        }
+    }
+
+    /// Gets the entry for the given key for in-place manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::{Entry, IntMap};
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// match map.entry(21) {
+    ///     Entry::Occupied(mut entry) => *entry.get_mut() += 1,
+    ///     Entry::Vacant(_) => unreachable!(),
+    /// }
+    /// assert_eq!(map.get(21), Some(&43));
+    ///
+    /// map.entry(7).or_insert(0);
+    /// assert_eq!(map.get(7), Some(&0));
+    /// ```
+    pub fn entry(&mut self, key: u64) -> Entry<'_, V> {
+        Entry::new(key, self)
+    }
+
+    /// Calls `f` with the entry of every key of `keys` in turn.
+    ///
+    /// Each entry is dropped before the next one is created, so `f` can insert into or
+    /// remove from the map through it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::{Entry, IntMap};
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 10);
+    ///
+    /// map.for_each_entry(&[1, 2], |entry| match entry {
+    ///     Entry::Occupied(mut e) => *e.get_mut() += 1,
+    ///     Entry::Vacant(e) => {
+    ///         e.insert(0);
+    ///     }
+    /// });
+    /// assert_eq!(map.get(1), Some(&11));
+    /// assert_eq!(map.get(2), Some(&0));
+    /// ```
+    pub fn for_each_entry<F: FnMut(Entry<'_, V>)>(&mut self, keys: &[u64], mut f: F) {
+        for key in keys {
+            f(self.entry(*key));
+        }
+    }
+
+    /// Starts a transaction whose inserts can be rolled back, see `TxGuard`.
+    ///
+    /// This is cheaper than cloning the map up front when a transaction only touches a
+    /// few keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    ///
+    /// let mut tx = map.begin_tx();
+    /// tx.insert(21, 42);
+    /// tx.commit();
+    ///
+    /// assert_eq!(map.get(21), Some(&42));
+    /// ```
+    pub fn begin_tx(&mut self) -> TxGuard<'_, V> {
+        TxGuard::new(self)
+    }
+
+    /// Returns a cursor over all entries that can remove the entry it is on, see
+    /// `CursorMut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..10).map(|i| (i, i)).collect();
+    ///
+    /// let mut cursor = map.cursor_mut();
+    /// while let Some((k, v)) = cursor.next() {
+    ///     if k % 2 == 0 {
+    ///         cursor.remove_current();
+    ///     } else {
+    ///         *v *= 10;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(map.len(), 5);
+    /// assert_eq!(map.get(3), Some(&30));
+    /// ```
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, V> {
+        CursorMut::new(self)
+    }
+
+    /// Remove value from the IntMap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// let val = map.remove(21);
+    /// assert!(val.is_some());
+    /// assert_eq!(val.unwrap(), 42);
+    /// assert!(!map.contains_key(21));
+    /// ```
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let ix = self.calc_index(key);
+
+        let ref mut vals = self.cache[ix];
+
+        if vals.len() > 0 {
+            for i in 0..vals.len() {
+                let peek = vals[i].0;
+
+                if peek == key {
+                    self.count -= 1;
+                    let kv = vals.swap_remove(i);
+                    self.record_remove(key);
+                    self.maybe_shrink();
+                    return Some(kv.1);
+                }
+            }
+
+            return None;
+        } else {
+            return None;
+        }
+    }
+
+    /// Remove value from the IntMap and push it onto `recycle` for later reuse, see
+    /// `insert_from`.
+    ///
+    /// Returns true if the key was present.
+    pub fn remove_into(&mut self, key: u64, recycle: &mut Vec<V>) -> bool {
+        match self.remove(key) {
+            Some(value) => {
+                recycle.push(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert a value taken from `pool` if the key is not yet inserted, after passing it to
+    /// `init`.
+    ///
+    /// If the pool is empty, `V::default()` is passed to `init` instead. This lets values
+    /// that own allocations, such as buffers, be reused instead of reallocated. Nothing is
+    /// taken from the pool and `init` does not run if the key is present. This function
+    /// returns true if key/value were inserted and false otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<Vec<u8>> = IntMap::new();
+    /// let mut pool = Vec::new();
+    ///
+    /// map.insert(1, Vec::with_capacity(1024));
+    /// map.remove_into(1, &mut pool);
+    ///
+    /// map.insert_from(2, &mut pool, |buf| {
+    ///     buf.clear();
+    ///     buf.push(42);
+    /// });
+    /// assert_eq!(map.get(2), Some(&vec![42]));
+    /// assert!(map.get(2).unwrap().capacity() >= 1024);
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn insert_from<F: FnOnce(&mut V)>(&mut self, key: u64, pool: &mut Vec<V>, init: F) -> bool
+    where
+        V: Default,
+    {
+        if self.contains_key(key) {
+            return false;
+        }
+
+        let mut value = pool.pop().unwrap_or_default();
+        init(&mut value);
+        self.insert_new(key, value);
+
+        true
+    }
+
+    /// Remove value from the IntMap only if `pred` returns true for it.
+    ///
+    /// The key is only looked up once. `pred` is not called if the key is absent, and the
+    /// value stays in the map if `pred` returns false.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// assert_eq!(map.remove_if(21, |v| *v > 50), None);
+    /// assert_eq!(map.remove_if(21, |v| *v == 42), Some(42));
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_if<F: FnOnce(&V) -> bool>(&mut self, key: u64, pred: F) -> Option<V> {
+        let ix = self.calc_index(key);
+
+        let vals = &mut self.cache[ix];
+        let pos = vals.iter().position(|kv| kv.0 == key)?;
+        if !pred(&vals[pos].1) {
+            return None;
+        }
+
+        self.count -= 1;
+        let kv = vals.swap_remove(pos);
+        self.record_remove(key);
+        self.maybe_shrink();
+        Some(kv.1)
+    }
+
+    /// Remove value from the IntMap, leaving a hole so `BucketSlot` handles to the other
+    /// entries of its bucket keep resolving without a bucket scan.
+    ///
+    /// `remove` fills the gap with the last entry of the bucket, which is O(1) but moves
+    /// that entry, so its handles have to fall back to scanning the bucket. This records
+    /// the position of the removed entry in a free-list of the bucket instead, which
+    /// handle lookups skip over. The remaining entries keep their iteration order.
+    ///
+    /// The price is a removal linear in the bucket length and one `usize` per hole, kept
+    /// until the bucket is emptied or the map is cleared, retained or resized. Only this
+    /// method maintains the free-list: removing from the same bucket any other way leaves
+    /// the handles into it stale, see `get_mut_or_slot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// assert_eq!(map.remove_keep_positions(21), Some(42));
+    /// assert_eq!(map.remove_keep_positions(21), None);
+    /// ```
+    pub fn remove_keep_positions(&mut self, key: u64) -> Option<V> {
+        let ix = self.calc_index(key);
+
+        let pos = self.cache[ix].iter().position(|kv| kv.0 == key)?;
+
+        let index = Self::slot_index(&self.slot_holes, ix, pos);
+        let kv = self.cache[ix].remove(pos);
+        match self.slot_holes.binary_search_by_key(&ix, |bh| bh.0) {
+            Ok(at) if self.cache[ix].is_empty() => {
+                self.slot_holes.remove(at);
+            }
+            Ok(at) => {
+                let holes = &mut self.slot_holes[at].1;
+                let at = holes.iter().take_while(|hole| **hole < index).count();
+                holes.insert(at, index);
+            }
+            Err(_) if self.cache[ix].is_empty() => {}
+            Err(at) => self.slot_holes.insert(at, (ix, vec![index])),
+        }
+
+        self.count -= 1;
+        self.record_remove(key);
+        Some(kv.1)
+    }
+
+    /// Removes and returns the entry with the largest key.
+    ///
+    /// The map is unordered, so this scans all entries and takes O(n) time. Fine for small
+    /// maps or occasional calls, but not as a priority queue over a large map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<&str> = IntMap::new();
+    /// map.insert(1, "one");
+    /// map.insert(3, "three");
+    ///
+    /// assert_eq!(map.pop_max(), Some((3, "three")));
+    /// assert_eq!(map.pop_max(), Some((1, "one")));
+    /// assert_eq!(map.pop_max(), None);
+    /// ```
+    pub fn pop_max(&mut self) -> Option<(u64, V)> {
+        let key = *self.keys().max()?;
+        self.remove(key).map(|v| (key, v))
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    ///
+    /// Like `pop_max`, this scans all entries and takes O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<&str> = IntMap::new();
+    /// map.insert(1, "one");
+    /// map.insert(3, "three");
+    ///
+    /// assert_eq!(map.pop_min(), Some((1, "one")));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<(u64, V)> {
+        let key = *self.keys().min()?;
+        self.remove(key).map(|v| (key, v))
+    }
+
+    /// Moves the value stored for `old` to the key `new`, without cloning it.
+    ///
+    /// Returns false and leaves the map unchanged if `old` is absent or `new` is already
+    /// present. Moving a present key to itself is a no-op returning true. The number of
+    /// elements never changes, so this never resizes the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<String> = IntMap::new();
+    /// map.insert(1, "value".to_string());
+    /// map.insert(2, "other".to_string());
+    ///
+    /// assert!(map.rekey(1, 3));
+    /// assert_eq!(map.get(3).map(|v| v.as_str()), Some("value"));
+    /// assert!(!map.contains_key(1));
+    ///
+    /// assert!(!map.rekey(1, 4));
+    /// assert!(!map.rekey(3, 2));
+    /// ```
+    pub fn rekey(&mut self, old: u64, new: u64) -> bool {
+        if old == new {
+            return self.contains_key(old);
+        }
+
+        if self.contains_key(new) {
+            return false;
+        }
+
+        let old_ix = self.calc_index(old);
+        let pos = match self.cache[old_ix].iter().position(|kv| kv.0 == old) {
+            Some(pos) => pos,
+            None => return false,
+        };
+
+        let (_, value) = self.cache[old_ix].swap_remove(pos);
+        self.record_remove(old);
+        let new_ix = self.calc_index(new);
+        self.cache[new_ix].push((new, value));
+        self.record_insert(new);
+
+        true
+    }
+
+    /// Swaps the values of keys `a` and `b` without moving or cloning them otherwise.
+    ///
+    /// Returns false and leaves the map unchanged if either key is missing. Swapping a key
+    /// with itself does nothing and returns whether it is present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<&str> = IntMap::new();
+    /// map.insert(1, "one");
+    /// map.insert(2, "two");
+    ///
+    /// assert!(map.swap(1, 2));
+    /// assert_eq!(map.get(1), Some(&"two"));
+    /// assert_eq!(map.get(2), Some(&"one"));
+    ///
+    /// assert!(!map.swap(1, 3));
+    /// assert_eq!(map.get(1), Some(&"two"));
+    /// ```
+    pub fn swap(&mut self, a: u64, b: u64) -> bool {
+        if a == b {
+            return self.contains_key(a);
+        }
+
+        let a_ix = self.calc_index(a);
+        let b_ix = self.calc_index(b);
+        let a_pos = match self.cache[a_ix].iter().position(|kv| kv.0 == a) {
+            Some(pos) => pos,
+            None => return false,
+        };
+        let b_pos = match self.cache[b_ix].iter().position(|kv| kv.0 == b) {
+            Some(pos) => pos,
+            None => return false,
+        };
+
+        if a_ix == b_ix {
+            let vals = &mut self.cache[a_ix];
+            let (lo, hi) = vals.split_at_mut(a_pos.max(b_pos));
+            std::mem::swap(&mut lo[a_pos.min(b_pos)].1, &mut hi[0].1);
+        } else {
+            let (lo, hi) = self.cache.split_at_mut(a_ix.max(b_ix));
+            let (lo_pos, hi_pos) = if a_ix < b_ix {
+                (a_pos, b_pos)
+            } else {
+                (b_pos, a_pos)
+            };
+            std::mem::swap(&mut lo[a_ix.min(b_ix)][lo_pos].1, &mut hi[0][hi_pos].1);
+        }
+
+        self.record_overwrite(a);
+        self.record_overwrite(b);
+        true
+    }
+
+    /// Returns true if key is in map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// assert!(map.contains_key(21));
+    /// ```
+    pub fn contains_key(&self, key: u64) -> bool {
+        match self.get(key) {
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Returns for every key of `keys`, in order, whether it is in the map.
+    ///
+    /// Keys are processed in small batches, hashing a whole batch before touching any
+    /// bucket, so the bucket loads of a batch do not wait on each other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 10);
+    /// map.insert(3, 30);
+    ///
+    /// assert_eq!(map.contains_mask(&[1, 2, 3, 1]), [true, false, true, true]);
+    /// ```
+    pub fn contains_mask(&self, keys: &[u64]) -> Vec<bool> {
+        const BATCH: usize = 8;
+
+        let mut mask = Vec::with_capacity(keys.len());
+        let mut buckets = [0usize; BATCH];
+
+        for batch in keys.chunks(BATCH) {
+            for (ix, key) in buckets.iter_mut().zip(batch) {
+                *ix = self.calc_index(*key);
+            }
+
+            for (ix, key) in buckets.iter().zip(batch) {
+                mask.push(self.cache[*ix].iter().any(|kv| kv.0 == *key));
+            }
+        }
+
+        mask
+    }
+
+    /// Removes all elements from map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// map.clear();
+    /// assert_eq!(map.len(), 0);
+    /// ```
+    pub fn clear(&mut self) {
+        for i in 0..self.cache.len() {
+            self.cache[i].clear();
+        }
+
+        self.record_clear();
+        self.count = 0;
+    }
+
+    /// Removes all elements from map and shrinks it back to the capacity of `IntMap::new`,
+    /// or to the floor set by `with_min_capacity`, freeing the memory held by the buckets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// for i in 0..1_000 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// map.clear_and_shrink();
+    /// assert_eq!(map.len(), 0);
+    /// assert_eq!(map.capacity(), IntMap::<u64>::new().capacity());
+    /// ```
+    pub fn clear_and_shrink(&mut self) {
+        let old_lim = self.cache.len();
+        self.cache = Vec::new();
+        self.record_clear();
+        self.count = 0;
+        let min_size = self.min_size;
+        self.rehash_to_size(min_size);
+        self.notify_resize(old_lim);
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements such that `f(key, &value)` returns false.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 11);
+    /// map.insert(2, 12);
+    /// map.insert(4, 13);
+    ///
+    /// // retain only the odd values
+    /// map.retain(|k, v| *v % 2 == 1);
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert!(map.contains_key(1));
+    /// assert!(map.contains_key(4));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(u64, &V) -> bool,
+    {
+        let mut removed = 0;
+        for i in 0..self.cache.len() {
+            #[cfg(feature = "track-removals")]
+            let removals = &mut self.removals;
+            #[cfg(feature = "entry-generations")]
+            let entry_generations = &mut self.entry_generations;
+            self.cache[i].retain(|(k, v)| {
+                let keep = (f)(*k, v);
+                if !keep {
+                    removed += 1;
+                    #[cfg(feature = "track-removals")]
+                    removals.record(*k);
+                    #[cfg(feature = "entry-generations")]
+                    entry_generations.remove(*k);
+                }
+                keep
+            });
+        }
+
+        self.count -= removed;
+        self.generation += removed as u64;
+        self.slot_holes.clear();
+        self.maybe_shrink();
+    }
+
+    /// Removes all entries for which `pred` returns true and returns them, in no particular
+    /// order.
+    ///
+    /// Unlike an iterator over the removed entries this does not borrow the map, so the map
+    /// can be used while handling them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..10).map(|i| (i, i * 10)).collect();
+    ///
+    /// let mut removed = map.filter_collect_remove(|k, _| k % 2 == 0);
+    /// removed.sort();
+    /// assert_eq!(removed, [(0, 0), (2, 20), (4, 40), (6, 60), (8, 80)]);
+    /// assert_eq!(map.len(), 5);
+    /// ```
+    pub fn filter_collect_remove<F: Fn(u64, &V) -> bool>(&mut self, pred: F) -> Vec<(u64, V)> {
+        let mut removed = Vec::new();
+        for ix in 0..self.cache.len() {
+            let mut i = 0;
+            while i < self.cache[ix].len() {
+                let (key, ref value) = self.cache[ix][i];
+                if pred(key, value) {
+                    removed.push(self.cache[ix].swap_remove(i));
+                    self.record_remove(key);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        self.count -= removed.len();
+        self.maybe_shrink();
+        removed
+    }
+
+    /// Removes every key of `keys` that is present, e.g. keys collected while iterating
+    /// with `iter`, which cannot remove entries itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..10).map(|i| (i, i)).collect();
+    ///
+    /// let stale: Vec<u64> = map.iter().filter(|(_, v)| **v > 6).map(|(k, _)| *k).collect();
+    /// map.remove_many_from_iter(stale);
+    /// assert_eq!(map.len(), 7);
+    /// ```
+    pub fn remove_many_from_iter(&mut self, keys: impl IntoIterator<Item = u64>) {
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// Calls `f` on every entry, stopping at and returning the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 11);
+    /// map.insert(2, 0);
+    ///
+    /// let res = map.try_for_each(|k, v| if *v == 0 { Err(k) } else { Ok(()) });
+    /// assert_eq!(res, Err(2));
+    /// ```
+    pub fn try_for_each<E, F>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(u64, &V) -> Result<(), E>,
+    {
+        for vals in self.cache.iter() {
+            for kv in vals.iter() {
+                f(kv.0, &kv.1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls `f` on every entry with mutable access to the value, stopping at and returning
+    /// the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 11);
+    ///
+    /// let res: Result<(), ()> = map.try_for_each_mut(|_, v| {
+    ///     *v += 1;
+    ///     Ok(())
+    /// });
+    /// assert!(res.is_ok());
+    /// assert_eq!(map.get(1), Some(&12));
+    /// ```
+    pub fn try_for_each_mut<E, F>(&mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(u64, &mut V) -> Result<(), E>,
+    {
+        for vals in self.cache.iter_mut() {
+            for kv in vals.iter_mut() {
+                f(kv.0, &mut kv.1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes the hash seed and rebuilds all buckets accordingly.
+    ///
+    /// Keys that collide under one seed are unlikely to collide under another one, so this
+    /// can be used to recover from pathological bucket lengths at runtime, see
+    /// `max_bucket_len`. All entries are kept. A seed of 0 restores the default hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// // Keys sharing their low bits all land in the same bucket by default.
+    /// let mut map: IntMap<u64> = (0..100).map(|i| (i << 20, i)).collect();
+    /// assert_eq!(map.max_bucket_len(), 100);
+    ///
+    /// map.reseed(0x5eed_5eed_5eed_5eed);
+    /// assert!(map.max_bucket_len() < 10);
+    /// assert_eq!(map.get(42 << 20), Some(&42));
+    /// ```
+    pub fn reseed(&mut self, new_seed: u64) {
+        self.seed = new_seed;
+
+        let size = self.size;
+        self.rehash_to_size(size);
+    }
+
+    /// Tries a few seeds and reseeds the map with the one giving the shortest longest bucket
+    /// for `sample`, hashed at the map's current capacity.
+    ///
+    /// This is a best-effort tuning step for static key sets, not a perfect hash: the
+    /// current seed is one of the candidates, so the longest bucket for the sample never
+    /// gets longer, but there is no guarantee it gets shorter or that keys outside the
+    /// sample benefit. All entries are kept. The map is only rebuilt if another seed wins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let keys: Vec<u64> = (0..100).map(|i| i << 20).collect();
+    /// let mut map: IntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+    /// assert_eq!(map.max_bucket_len(), 100);
+    ///
+    /// map.optimize_for(&keys);
+    /// assert!(map.max_bucket_len() < 10);
+    /// ```
+    pub fn optimize_for(&mut self, sample: &[u64]) {
+        const CANDIDATES: u64 = 16;
+
+        let mut lengths = vec![0u32; self.cache.len()];
+        let mut longest = |seed: u64| {
+            for len in lengths.iter_mut() {
+                *len = 0;
+            }
+
+            let mut max = 0;
+            for key in sample {
+                let ix = (IntMap::<V>::seeded_hash(*key, seed) & self.mod_mask) as usize;
+                lengths[ix] += 1;
+                max = max.max(lengths[ix]);
+            }
+            max
+        };
+
+        let mut best_seed = self.seed;
+        let mut best = longest(self.seed);
+
+        // Odd multiples of the golden ratio constant give well spread, non-zero seeds.
+        let mut candidate = 0u64;
+        for _ in 0..CANDIDATES {
+            candidate = candidate.wrapping_add(HASH_MULTIPLIER);
+            let max = longest(candidate);
+            if max < best {
+                best = max;
+                best_seed = candidate;
+            }
+        }
+
+        if best_seed != self.seed {
+            self.reseed(best_seed);
+        }
+    }
+
+    /// Reseeds the map as with `optimize_for` over all its keys, but only if its
+    /// `distribution_quality` exceeds `threshold`. Returns true if the map was reseeded.
+    ///
+    /// Checking a healthy map only scans the bucket lengths, so this is cheap enough to call
+    /// periodically. All entries are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut clustered: IntMap<u64> = (0..1_000).map(|i| (i << 32, i)).collect();
+    /// assert!(clustered.rehash_if_degraded(4.0));
+    /// assert!(clustered.distribution_quality() <= 4.0);
+    ///
+    /// let mut spread: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+    /// assert!(!spread.rehash_if_degraded(4.0));
+    /// ```
+    pub fn rehash_if_degraded(&mut self, threshold: f64) -> bool {
+        if self.distribution_quality() <= threshold {
+            return false;
+        }
+
+        let seed = self.seed;
+        let keys: Vec<u64> = self.keys_copied().collect();
+        self.optimize_for(&keys);
+        self.seed != seed
+    }
+
+    /// The seed the keys are hashed with, see `reseed`.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns true if no key is present in both maps.
+    ///
+    /// Iterates the smaller map and probes the larger one, stopping at the first shared key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut a: IntMap<u64> = IntMap::new();
+    /// a.insert(1, 10);
+    /// a.insert(2, 20);
+    ///
+    /// let mut b: IntMap<&str> = IntMap::new();
+    /// b.insert(3, "three");
+    /// assert!(a.is_disjoint(&b));
+    ///
+    /// b.insert(2, "two");
+    /// assert!(!a.is_disjoint(&b));
+    /// ```
+    pub fn is_disjoint<W>(&self, other: &IntMap<W>) -> bool {
+        if self.len() <= other.len() {
+            self.keys().all(|k| !other.contains_key(*k))
+        } else {
+            other.keys().all(|k| !self.contains_key(*k))
+        }
+    }
+
+    /// Returns true if every key in this map is also present in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut a: IntMap<u64> = IntMap::new();
+    /// a.insert(1, 10);
+    ///
+    /// let mut b: IntMap<&str> = IntMap::new();
+    /// b.insert(1, "one");
+    /// b.insert(2, "two");
+    ///
+    /// assert!(a.is_key_subset(&b));
+    /// assert!(!b.is_key_subset(&a));
+    /// ```
+    pub fn is_key_subset<W>(&self, other: &IntMap<W>) -> bool {
+        if self.len() > other.len() {
+            return false;
+        }
+
+        self.keys().all(|k| other.contains_key(*k))
+    }
+
+    /// Iterates over the union of the keys of both maps, tagging every entry with the map it
+    /// comes from.
+    ///
+    /// Keys present in both maps are yielded once, with the value of this map and tagged
+    /// `Origin::Both`. Entries of this map come first, then those only in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::{IntMap, Origin};
+    ///
+    /// let mut a: IntMap<&str> = IntMap::new();
+    /// a.insert(1, "a1");
+    /// a.insert(2, "a2");
+    ///
+    /// let mut b: IntMap<&str> = IntMap::new();
+    /// b.insert(2, "b2");
+    /// b.insert(3, "b3");
+    ///
+    /// let mut tagged: Vec<_> = a.tagged_iter(&b).collect();
+    /// tagged.sort_by_key(|t| t.0);
+    /// assert_eq!(
+    ///     tagged,
+    ///     [(1, &"a1", Origin::Left), (2, &"a2", Origin::Both), (3, &"b3", Origin::Right)]
+    /// );
+    /// ```
+    pub fn tagged_iter<'a>(
+        &'a self,
+        other: &'a IntMap<V>,
+    ) -> impl Iterator<Item = (u64, &'a V, Origin)> + 'a {
+        let left = self.iter().map(move |(k, v)| {
+            if other.contains_key(*k) {
+                (*k, v, Origin::Both)
+            } else {
+                (*k, v, Origin::Left)
+            }
+        });
+        let right = other
+            .iter()
+            .filter(move |(k, _)| !self.contains_key(**k))
+            .map(|(k, v)| (*k, v, Origin::Right));
+
+        left.chain(right)
+    }
+
+    /// Adds every value of `other` to the value with the same key in this map, inserting a
+    /// copy of it where the key is absent.
+    ///
+    /// Treating both maps as sparse vectors, this is element-wise vector addition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut a: IntMap<u64> = IntMap::new();
+    /// a.insert(1, 10);
+    /// a.insert(2, 20);
+    ///
+    /// let mut b: IntMap<u64> = IntMap::new();
+    /// b.insert(2, 2);
+    /// b.insert(3, 3);
+    ///
+    /// a.add_assign_map(&b);
+    /// assert_eq!(a.get(1), Some(&10));
+    /// assert_eq!(a.get(2), Some(&22));
+    /// assert_eq!(a.get(3), Some(&3));
+    /// ```
+    pub fn add_assign_map(&mut self, other: &IntMap<V>)
+    where
+        V: std::ops::AddAssign + Copy,
+    {
+        for (k, v) in other.iter() {
+            match self.entry(*k) {
+                Entry::Occupied(mut entry) => *entry.get_mut() += *v,
+                Entry::Vacant(entry) => {
+                    entry.insert(*v);
+                }
+            }
+        }
+    }
+
+    /// Returns a 64-bit digest of the contents that does not depend on iteration order.
+    ///
+    /// Every `(key, value)` pair is hashed together with `seed` and the results are
+    /// summed up with wrapping addition, so maps with equal contents have equal digests
+    /// regardless of their capacity, seed or insertion order.
+    ///
+    /// The pairs are hashed by feeding every integer written by `Hash` through the
+    /// splitmix64 finalizer, in little-endian byte order and with `usize` and `isize`
+    /// widened to 64 bits. The digest is thus the same across builds, platforms and crate
+    /// versions as long as the `Hash` impl of `V` is, which holds for integers, strings and
+    /// their tuples and vectors, so replicas on different machines can compare digests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let a: IntMap<u64> = (0..100).map(|i| (i, i * 2)).collect();
+    /// let b: IntMap<u64> = (0..100).rev().map(|i| (i, i * 2)).collect();
+    /// assert_eq!(a.content_hash(7), b.content_hash(7));
+    /// ```
+    pub fn content_hash(&self, seed: u64) -> u64
+    where
+        V: std::hash::Hash,
+    {
+        use std::hash::Hasher;
+
+        self.iter().fold(0u64, |digest, (k, v)| {
+            let mut hasher = StableHasher(mix(seed));
+            hasher.write_u64(*k);
+            v.hash(&mut hasher);
+            digest.wrapping_add(hasher.finish())
+        })
+    }
+
+    /// Counter increased by every insert of a new key and every removal.
+    ///
+    /// Two calls returning the same value mean no key was added or removed in between.
+    /// Overwriting values does not change it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// let start = map.generation();
+    ///
+    /// map.insert(21, 42);
+    /// map.remove(21);
+    /// assert_eq!(map.generation(), start + 2);
+    /// ```
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns true if map is empty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    /// assert!(!map.is_empty());
+    /// map.remove(21);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn is_empty(&mut self) -> bool {
+        self.count == 0
+    }
+
+    //**** Iterators *****
+
+    /// Iterates over all entries in an unspecified order.
+    ///
+    /// All iterators walk the buckets the same way, so for a map that is not modified in
+    /// between, `iter`, `iter_mut`, `keys`, `values`, `values_mut`, `drain` and `into_iter`
+    /// visit the entries in the same order. In particular `keys()` and `values()` can be
+    /// zipped back into pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i * 2)).collect();
+    ///
+    /// for (k, v) in map.keys().zip(map.values()) {
+    ///     assert_eq!(*k * 2, *v);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<u64, V> {
+        Iter::new(&self.cache)
+    }
+
+    /// Iterates over all entries with mutable access to the values.
+    ///
+    /// # Address stability
+    ///
+    /// Values are stored inline in per-bucket vectors. The address of a value stays the
+    /// same as long as the map is not structurally modified: reads (`get`, `iter`, ...) and
+    /// in-place value mutation never move entries. Inserting may reallocate a bucket or
+    /// resize the whole map, removing may move another entry of the same bucket into the
+    /// freed position, and `clear`/`drain` drop or move everything. The borrow checker
+    /// already rules out holding a `&mut V` across any of these:
+    ///
+    /// ```compile_fail
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 1);
+    ///
+    /// let val = map.get_mut(1).unwrap();
+    /// map.insert(2, 2); // error: the map is still mutably borrowed
+    /// *val += 1;
+    /// ```
+    ///
+    /// Addresses only need extra care when turned into raw pointers.
+    pub fn iter_mut(&mut self) -> IterMut<u64, V> {
+        IterMut::new(&mut self.cache)
+    }
+
+    /// Iterates over all entries like `iter`, but yields keys by value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i * 2)).collect();
+    ///
+    /// for (k, v) in map.iter_copied() {
+    ///     assert_eq!(k * 2, *v);
+    /// }
+    /// ```
+    pub fn iter_copied(&self) -> impl Iterator<Item = (u64, &V)> + '_ {
+        self.iter().map(|(k, v)| (*k, v))
+    }
+
+    /// The `n`-th entry in iteration order, the same as `iter().nth(n)`.
+    ///
+    /// Whole buckets are skipped by their length, so this is O(capacity) rather than O(n)
+    /// entries visited. Since the iteration order of a map only changes when it is
+    /// modified, this gives reproducible samples of an unmodified map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    ///
+    /// assert_eq!(map.nth_entry(42), map.iter().nth(42).map(|(k, v)| (*k, v)));
+    /// assert_eq!(map.nth_entry(100), None);
+    /// ```
+    pub fn nth_entry(&self, mut n: usize) -> Option<(u64, &V)> {
+        for vals in self.cache.iter() {
+            if n < vals.len() {
+                return Some((vals[n].0, &vals[n].1));
+            }
+            n -= vals.len();
+        }
+
+        None
+    }
+
+    /// The entries at the given positions in iteration order, see `nth_entry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    ///
+    /// let sample = map.sample(&[0, 50, 200]);
+    /// assert_eq!(sample[1], map.nth_entry(50));
+    /// assert_eq!(sample[2], None);
+    /// ```
+    pub fn sample(&self, indices: &[usize]) -> Vec<Option<(u64, &V)>> {
+        indices.iter().map(|n| self.nth_entry(*n)).collect()
+    }
+
+    pub fn keys(&self) -> Keys<u64, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Iterates over all keys by value.
+    ///
+    /// Collecting the keys first is the simplest way to update values while walking the
+    /// keys, as the collected keys no longer borrow the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..100).map(|i| (i, 0)).collect();
+    ///
+    /// let keys: Vec<u64> = map.keys_copied().collect();
+    /// for k in keys {
+    ///     *map.get_mut(k).unwrap() = k * 2;
+    /// }
+    ///
+    /// for (k, v) in map.iter() {
+    ///     assert_eq!(*k * 2, *v);
+    /// }
+    /// ```
+    pub fn keys_copied(&self) -> impl Iterator<Item = u64> + '_ {
+        self.cache
+            .iter()
+            .flat_map(|vals| vals.iter().map(|kv| kv.0))
+    }
+
+    pub fn values(&self) -> Values<u64, V> {
+        Values { inner: self.iter() }
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<u64, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    /// Iterates over all entries in ascending key order, with mutable access to the values.
+    ///
+    /// The mutable references are collected and sorted up front, so this allocates one
+    /// vector of `len()` entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = vec![(3, 0), (1, 0), (2, 0)].into_iter().collect();
+    ///
+    /// let mut next = 10;
+    /// for (_, v) in map.values_mut_sorted() {
+    ///     *v = next;
+    ///     next += 1;
+    /// }
+    /// assert_eq!(map.into_sorted_vec(), [(1, 10), (2, 11), (3, 12)]);
+    /// ```
+    pub fn values_mut_sorted(&mut self) -> std::vec::IntoIter<(u64, &mut V)> {
+        let mut entries: Vec<_> = self.iter_mut().map(|(k, v)| (*k, v)).collect();
+        entries.sort_unstable_by_key(|kv| kv.0);
+        entries.into_iter()
+    }
+
+    pub fn drain(&mut self) -> Drain<u64, V> {
+        self.record_clear();
+        Drain::new(&mut self.cache, &mut self.count)
+    }
+
+    /// Moves all entries into a new vector, leaving the map empty with its capacity intact.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// assert_eq!(map.drain_to_vec(), [(21, 42)]);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain_to_vec(&mut self) -> Vec<(u64, V)> {
+        let mut entries = Vec::with_capacity(self.count);
+        for vals in self.cache.iter_mut() {
+            entries.append(vals);
+        }
+
+        self.record_clear();
+        self.count = 0;
+        entries
+    }
+
+    /// Consumes the map into a vector of its entries sorted by key.
+    ///
+    /// Together with `from_sorted_vec` this gives a canonical representation of a map's
+    /// contents, independent of its capacity and seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = vec![(3, 30), (1, 10), (2, 20)].into_iter().collect();
+    /// assert_eq!(map.into_sorted_vec(), [(1, 10), (2, 20), (3, 30)]);
+    /// ```
+    pub fn into_sorted_vec(mut self) -> Vec<(u64, V)> {
+        let mut entries = self.drain_to_vec();
+        entries.sort_unstable_by_key(|kv| kv.0);
+        entries
+    }
+
+    /// Creates a new map with the same keys and values transformed by `f`.
+    ///
+    /// The new map has the same capacity and seed, so every entry is copied into the same
+    /// bucket without hashing its key again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..10).map(|i| (i, i)).collect();
+    /// let strings: IntMap<String> = map.map_values(|v| v.to_string());
+    /// assert_eq!(strings.get(7).map(|s| s.as_str()), Some("7"));
+    /// ```
+    pub fn map_values<W, F: FnMut(&V) -> W>(&self, mut f: F) -> IntMap<W> {
+        let mut map = IntMap::with_shape_of(self);
+        for (dst, src) in map.cache.iter_mut().zip(self.cache.iter()) {
+            dst.extend(src.iter().map(|kv| (kv.0, f(&kv.1))));
+        }
+
+        map.adopt_entries();
+        map
+    }
+
+    /// Consumes the map into a new one with the same keys and values transformed by `f`,
+    /// see `map_values`.
+    pub fn into_map_values<W, F: FnMut(V) -> W>(self, mut f: F) -> IntMap<W> {
+        let mut map = IntMap::with_shape_of(&self);
+        for (dst, src) in map.cache.iter_mut().zip(self.cache) {
+            dst.extend(src.into_iter().map(|kv| (kv.0, f(kv.1))));
+        }
+
+        map.adopt_entries();
+        map
+    }
+
+    /// Creates a new map holding clones of the entries for which `pred` returns true.
+    ///
+    /// Unlike `retain` this leaves the map untouched. The new map keeps the seed and is
+    /// sized for the matching entries only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    /// let even = map.filter(|k, _| k % 2 == 0);
+    ///
+    /// assert_eq!(even.len(), 50);
+    /// assert_eq!(map.len(), 100);
+    /// ```
+    pub fn filter<F: Fn(u64, &V) -> bool>(&self, pred: F) -> IntMap<V>
+    where
+        V: Clone,
+    {
+        let entries: Vec<(u64, V)> = self
+            .iter()
+            .filter(|(k, v)| pred(**k, v))
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+
+        IntMap::from_unique_entries(entries, self.seed)
+    }
+
+    /// Consumes the map into a new one holding only the entries for which `pred` returns
+    /// true, moving the values instead of cloning them, see `filter`.
+    pub fn into_filtered<F: Fn(u64, &V) -> bool>(self, pred: F) -> IntMap<V> {
+        let seed = self.seed;
+        let entries: Vec<(u64, V)> = self.into_iter().filter(|(k, v)| pred(*k, v)).collect();
+
+        IntMap::from_unique_entries(entries, seed)
+    }
+
+    /// The bucket table itself: slot `i` holds the entries whose key hashes to `i`.
+    ///
+    /// Together with `seed` this is the complete layout of the map, which
+    /// `from_raw_buckets` restores without rehashing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// let buckets = map.raw_buckets();
+    /// assert_eq!(buckets.len(), map.capacity());
+    /// assert_eq!(buckets.iter().flatten().collect::<Vec<_>>(), [&(21, 42)]);
+    /// ```
+    pub fn raw_buckets(&self) -> &[Vec<(u64, V)>] {
+        &self.cache
+    }
+
+    /// Creates a map from a bucket table as returned by `raw_buckets`, using `seed` to
+    /// hash keys.
+    ///
+    /// The table is trusted to have the layout of a map with this seed: every entry is in
+    /// the bucket its key hashes to, and no key occurs twice. Nothing is moved or checked
+    /// for duplicates, so breaking this leaves a map whose lookups miss entries and whose
+    /// `len` is wrong. Debug builds check the layout and panic if it does not hold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the table is not a power of two.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// let buckets = map.raw_buckets().to_vec();
+    /// let restored = IntMap::from_raw_buckets(buckets, map.seed());
+    /// assert_eq!(restored, map);
+    /// ```
+    pub fn from_raw_buckets(buckets: Vec<Vec<(u64, V)>>, seed: u64) -> Self {
+        assert!(
+            buckets.len().is_power_of_two(),
+            "bucket table length {} is not a power of two",
+            buckets.len()
+        );
+
+        let mut map = IntMap::with_size(buckets.len().trailing_zeros());
+        map.seed = seed;
+        map.cache = buckets;
+        map.count = map.cache.iter().map(|vals| vals.len()).sum();
+
+        for ix in 0..map.cache.len() {
+            for i in 0..map.cache[ix].len() {
+                let key = map.cache[ix][i].0;
+                debug_assert!(
+                    map.calc_index(key) == ix && !map.cache[ix][..i].iter().any(|kv| kv.0 == key),
+                    "key {} misplaced or duplicated in bucket {}",
+                    key,
+                    ix
+                );
+                map.record_insert(key);
+            }
+        }
+
+        map
+    }
+
+    /// Consumes the map, distributing its entries over `n` new maps.
+    ///
+    /// Each key goes to the shard picked by the upper bits of its multiplicative hash, so
+    /// shards stay balanced even for keys sharing a stride, and a key always ends up in the
+    /// same shard for a given `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+    /// let shards = map.into_shards(4);
+    ///
+    /// assert_eq!(shards.len(), 4);
+    /// assert_eq!(shards.iter().map(|s| s.len()).sum::<usize>(), 1_000);
+    /// ```
+    pub fn into_shards(self, n: usize) -> Vec<IntMap<V>> {
+        assert!(n > 0, "cannot split a map into zero shards");
+
+        let mut shards: Vec<IntMap<V>> = (0..n)
+            .map(|_| IntMap::with_capacity(self.count / n))
+            .collect();
+
+        for (k, v) in self {
+            let hash = HASH_MULTIPLIER.wrapping_mul(k);
+            let shard = ((hash as u128 * n as u128) >> 64) as usize;
+            shards[shard].insert(k, v);
+        }
+
+        shards
+    }
+
+    /// Creates a map from entries sorted by key, such as the output of `into_sorted_vec`.
+    ///
+    /// The map is sized for all entries up front. The keys are trusted to be unique, so
+    /// no entry is checked for an already present key; the ordering itself is not needed
+    /// for correctness, only uniqueness is. Passing duplicate keys leaves the map with
+    /// unreachable entries and a wrong `len`. Debug builds check that the keys are
+    /// strictly increasing and panic otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map = IntMap::from_sorted_vec(vec![(1, 10), (2, 20), (3, 30)]);
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.get(2), Some(&20));
+    /// ```
+    pub fn from_sorted_vec(entries: Vec<(u64, V)>) -> Self {
+        debug_assert!(
+            entries.windows(2).all(|w| w[0].0 < w[1].0),
+            "entries passed to from_sorted_vec are not sorted by unique keys"
+        );
+
+        let mut map = IntMap::with_capacity(entries.len());
+        map.count = entries.len();
+        for kv in entries {
+            let ix = map.calc_index(kv.0);
+            map.record_insert(kv.0);
+            map.cache[ix].push(kv);
+        }
+
+        map
+    }
+
+    /// Iterates over all entries together with the index of the bucket they are stored in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(21, 42);
+    ///
+    /// for (bucket, key, value) in map.iter_with_bucket() {
+    ///     assert!(bucket < map.capacity());
+    ///     assert_eq!((key, *value), (21, 42));
+    /// }
+    /// ```
+    pub fn iter_with_bucket(&self) -> impl Iterator<Item = (usize, u64, &V)> {
+        self.cache
+            .iter()
+            .enumerate()
+            .flat_map(|(ix, vals)| vals.iter().map(move |kv| (ix, kv.0, &kv.1)))
+    }
+
+    /// Iterates over the entries for which `pred` returns true.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..10).map(|i| (i, i * 10)).collect();
+    ///
+    /// let mut even: Vec<(u64, &u64)> = map.iter_filter(|k, _| k % 2 == 0).collect();
+    /// even.sort();
+    /// assert_eq!(even, [(0, &0), (2, &20), (4, &40), (6, &60), (8, &80)]);
+    /// ```
+    pub fn iter_filter<'a, F>(&'a self, pred: F) -> impl Iterator<Item = (u64, &'a V)> + 'a
+    where
+        F: Fn(u64, &V) -> bool + 'a,
+    {
+        self.iter()
+            .filter(move |(k, v)| pred(**k, v))
+            .map(|(k, v)| (*k, v))
+    }
+
+    /// Iterates mutably over the entries for which `pred` returns true.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..10).map(|i| (i, i * 10)).collect();
+    ///
+    /// for (_, v) in map.iter_filter_mut(|k, _| k % 2 == 1) {
+    ///     *v = 0;
+    /// }
+    /// assert_eq!(map.get(2), Some(&20));
+    /// assert_eq!(map.get(3), Some(&0));
+    /// ```
+    pub fn iter_filter_mut<'a, F>(
+        &'a mut self,
+        pred: F,
+    ) -> impl Iterator<Item = (u64, &'a mut V)> + 'a
+    where
+        F: Fn(u64, &V) -> bool + 'a,
+    {
+        self.iter_mut()
+            .filter(move |(k, v)| pred(**k, v))
+            .map(|(k, v)| (*k, v))
+    }
+
+    /// Iterates over the entries inserted at or after generation `gen`, as returned by
+    /// `generation`.
+    ///
+    /// Only new keys count as inserted; overwriting the value of a present key does not
+    /// change its generation, while a key moved by `rekey` counts as newly inserted.
+    /// Requires the `change-tracking` feature, which stores one generation per key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 10);
+    ///
+    /// let mark = map.generation();
+    /// map.insert(2, 20);
+    ///
+    /// assert_eq!(map.iter_since(mark).collect::<Vec<_>>(), [(2, &20)]);
+    /// ```
+    #[cfg(feature = "change-tracking")]
+    pub fn iter_since(&self, gen: u64) -> impl Iterator<Item = (u64, &V)> {
+        self.iter()
+            .filter(move |(k, _)| matches!(self.inserted_at.get(k), Some(at) if *at >= gen))
+            .map(|(k, v)| (*k, v))
+    }
+
+    /// The generation of the entry for `key`, or `None` if the key is not in the map.
+    ///
+    /// Unlike the map-wide `generation`, this is a counter per entry. It is bumped when
+    /// the key is inserted, when its value is overwritten by `insert_mut`,
+    /// `insert_counting` or `OccupiedEntry::insert`, and by `bump_generation`. Mutating a
+    /// value in place through `get_mut` does not bump it. A newly inserted key starts above
+    /// the generation of every key removed before, so a `(key, generation)` pair taken
+    /// earlier never matches an entry that was removed and inserted again. Counters wrap
+    /// around after `u32::MAX` bumps.
+    ///
+    /// Requires the `entry-generations` feature, which stores one `u32` per entry. Removed
+    /// keys are forgotten.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 10);
+    /// let handle = (1, map.generation_of(1).unwrap());
+    ///
+    /// map.remove(1);
+    /// map.insert(1, 11);
+    /// assert_ne!(map.generation_of(handle.0), Some(handle.1));
+    /// ```
+    #[cfg(feature = "entry-generations")]
+    pub fn generation_of(&self, key: u64) -> Option<u32> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        Some(self.entry_generations.get(key))
+    }
+
+    /// Bumps the generation of the entry for `key` without touching its value, and returns
+    /// the new generation, or `None` if the key is not in the map. See `generation_of`.
+    ///
+    /// Requires the `entry-generations` feature.
+    #[cfg(feature = "entry-generations")]
+    pub fn bump_generation(&mut self, key: u64) -> Option<u32> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        Some(self.entry_generations.bump(key))
+    }
+
+    /// The most recently removed keys, at most as many as the history set with
+    /// `with_removal_history`, 16 by default. Only keys are kept, never values.
+    ///
+    /// Keys are in removal order, oldest first. Once the history is full, every removal
+    /// overwrites the oldest key in place, so recording stays O(1) for any history size.
+    /// Keys dropped by `clear` or `drain` are not recorded.
+    ///
+    /// Requires the `track-removals` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_removal_history(2);
+    /// for i in 0..4 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// map.remove(0);
+    /// map.remove(1);
+    /// assert!(map.recent_removals().eq([0, 1]));
+    ///
+    /// map.remove(2);
+    /// assert!(map.recent_removals().eq([1, 2]));
+    /// ```
+    #[cfg(feature = "track-removals")]
+    pub fn recent_removals(&self) -> impl Iterator<Item = u64> + '_ {
+        self.removals.iter()
+    }
+
+    /// Returns all entries sorted by value, largest first.
+    ///
+    /// This collects and sorts all entries, see `top_n` if only the largest few are needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (1..4).map(|i| (i, i * 10)).collect();
+    /// let sorted: Vec<_> = map.iter_by_value_desc().collect();
+    /// assert_eq!(sorted, [(3, &30), (2, &20), (1, &10)]);
+    /// ```
+    pub fn iter_by_value_desc(&self) -> std::vec::IntoIter<(u64, &V)>
+    where
+        V: Ord,
+    {
+        let mut entries: Vec<(u64, &V)> = self.iter().map(|(k, v)| (*k, v)).collect();
+        entries.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(&b.0)));
+        entries.into_iter()
+    }
+
+    /// Iterates over the entries in ascending key order, merging runs of consecutive keys
+    /// with equal values into `(start_key, end_key, value)`, both ends inclusive.
+    ///
+    /// Only keys that follow each other directly form a run, so a gap between two keys
+    /// always starts a new run even if their values are equal. This suits maps holding
+    /// piecewise constant data over contiguous keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<char> = vec![(1, 'a'), (2, 'a'), (3, 'b'), (5, 'b')].into_iter().collect();
+    ///
+    /// let runs: Vec<_> = map.iter_sorted_rle().collect();
+    /// assert_eq!(runs, [(1, 2, &'a'), (3, 3, &'b'), (5, 5, &'b')]);
+    /// ```
+    pub fn iter_sorted_rle(&self) -> std::vec::IntoIter<(u64, u64, &V)>
+    where
+        V: PartialEq,
+    {
+        let mut entries: Vec<(u64, &V)> = self.iter().map(|(k, v)| (*k, v)).collect();
+        entries.sort_unstable_by_key(|kv| kv.0);
+
+        let mut runs: Vec<(u64, u64, &V)> = Vec::new();
+        for (k, v) in entries {
+            match runs.last_mut() {
+                Some(run) if run.1.checked_add(1) == Some(k) && run.2 == v => run.1 = k,
+                _ => runs.push((k, k, v)),
+            }
+        }
+
+        runs.into_iter()
+    }
+
+    /// Returns the `n` entries with the largest values, largest first.
+    ///
+    /// Uses a heap bounded to `n` entries instead of sorting the whole map. Entries with
+    /// equal values are ordered by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut counts: IntMap<u64> = IntMap::new();
+    /// counts.insert(1, 5);
+    /// counts.insert(2, 50);
+    /// counts.insert(3, 7);
+    ///
+    /// assert_eq!(counts.top_n(2), [(2, &50), (3, &7)]);
+    /// ```
+    pub fn top_n(&self, n: usize) -> Vec<(u64, &V)>
+    where
+        V: Ord,
+    {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        if n == 0 {
+            return Vec::new();
+        }
+
+        // Min-heap of the best entries seen so far, the root is the first to be evicted.
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for (k, v) in self.iter() {
+            heap.push(Reverse((v, Reverse(*k))));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((v, Reverse(k)))| (k, v))
+            .collect()
+    }
+
+    /// Looks up every key of `keys` in order, yielding its value or `None` if absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<&str> = IntMap::new();
+    /// map.insert(1, "one");
+    /// map.insert(3, "three");
+    ///
+    /// let values: Vec<_> = map.values_in_order(&[1, 2, 3]).collect();
+    /// assert_eq!(values, [Some(&"one"), None, Some(&"three")]);
+    /// ```
+    pub fn values_in_order<'a>(
+        &'a self,
+        keys: &'a [u64],
+    ) -> impl Iterator<Item = Option<&'a V>> + 'a {
+        keys.iter().map(move |k| self.get(*k))
+    }
+
+    //**** Internal hash stuff *****
+
+    #[inline]
+    fn hash_u64(&self, key: u64) -> u64 {
+        IntMap::<V>::seeded_hash(key, self.seed)
+    }
+
+    #[inline]
+    fn seeded_hash(key: u64, seed: u64) -> u64 {
+        if seed == 0 {
+            HASH_MULTIPLIER.wrapping_mul(key)
+        } else {
+            // The low bits of a plain product only depend on the low bits of the key, so
+            // seeded maps run the key through a finalizer that mixes all of its bits.
+            mix(key ^ seed)
+        }
+    }
+
+    #[inline]
+    fn calc_index(&self, key: u64) -> usize {
+        let hash = self.hash_u64(key);
+        // Faster modulus
+        (hash & self.mod_mask) as usize
+    }
+
+    #[inline]
+    fn lim(&self) -> usize {
+        2u64.pow(self.size) as usize
+    }
+
+    fn increase_cache(&mut self) {
+        let size = self.size + 1;
+        self.rehash_to_size(size);
+    }
+
+    fn rehash_to_size(&mut self, size: u32) {
+        #[cfg(feature = "stats")]
+        self.stats.record_resize();
+
+        let old_lim = self.cache.len();
+        self.size = size;
+        self.slot_holes.clear();
+        let new_lim = self.lim();
+        self.mod_mask = (new_lim as u64) - 1;
+
+        let mut vec: Vec<Vec<(u64, V)>> = Vec::new();
+
+        vec.append(&mut self.cache);
+
+        for _ in 0..new_lim {
+            self.cache.push(Vec::with_capacity(self.bucket_capacity));
+        }
+
+        while vec.len() > 0 {
+            let mut values = vec.pop().unwrap();
+            while values.len() > 0 {
+                if let Some(k) = values.pop() {
+                    let ix = self.calc_index(k.0);
+
+                    let ref mut vals = self.cache[ix];
+                    vals.push(k);
+                }
+            }
+        }
+
+        debug_assert!(
+            self.cache.len() == self.lim(),
+            "cache vector the wrong length, lim: {:?} cache: {:?}",
+            self.lim(),
+            self.cache.len()
+        );
+        // The fast modulus in calc_index relies on this.
+        debug_assert!(
+            self.cache.len().is_power_of_two() && self.mod_mask == (self.cache.len() as u64) - 1,
+            "cache vector not a power of two, cache: {:?} mod_mask: {:?}",
+            self.cache.len(),
+            self.mod_mask
+        );
+
+        self.notify_resize(old_lim);
+    }
+
+    /// Builds a map with the given seed from entries known to have distinct keys.
+    fn from_unique_entries(entries: Vec<(u64, V)>, seed: u64) -> Self {
+        let mut map = IntMap::with_capacity(entries.len());
+        if seed != 0 {
+            map.reseed(seed);
+        }
+
+        for kv in entries {
+            let ix = map.calc_index(kv.0);
+            map.cache[ix].push(kv);
+        }
+
+        map.adopt_entries();
+        map
+    }
+
+    /// Updates the bookkeeping after entries were written to the buckets directly.
+    fn adopt_entries(&mut self) {
+        self.count = 0;
+        for ix in 0..self.cache.len() {
+            for i in 0..self.cache[ix].len() {
+                let key = self.cache[ix][i].0;
+                self.record_insert(key);
+            }
+            self.count += self.cache[ix].len();
+        }
+    }
+
+    fn notify_resize(&mut self, old_lim: usize) {
+        let new_lim = self.cache.len();
+        // A table built from scratch is not a resize.
+        if old_lim == 0 || old_lim == new_lim {
+            return;
+        }
+
+        if let Some(f) = self.on_resize.0.as_mut() {
+            f(old_lim, new_lim);
+        }
+    }
+
+    /// Inserts a key that is known to be absent and returns a reference to its value.
+    fn insert_new(&mut self, key: u64, value: V) -> &mut V {
+        self.count += 1;
+        self.record_insert(key);
+        // Grow before pushing so the new entry does not move afterwards.
+        if (self.count & 4) == 4 {
+            self.ensure_load_rate();
+        }
+
+        let ix = self.calc_index(key);
+        let vals = &mut self.cache[ix];
+        vals.push((key, value));
+        &mut vals.last_mut().unwrap().1
+    }
+
+    #[inline]
+    fn record_insert(&mut self, _key: u64) {
+        #[cfg(feature = "change-tracking")]
+        self.inserted_at.insert(_key, self.generation);
+
+        #[cfg(feature = "entry-generations")]
+        self.entry_generations.bump(_key);
+
+        self.generation += 1;
+    }
+
+    // Called when the value of a present key is overwritten.
+    #[inline]
+    fn record_overwrite(&mut self, _key: u64) {
+        #[cfg(feature = "entry-generations")]
+        self.entry_generations.bump(_key);
+    }
+
+    #[inline]
+    fn record_remove(&mut self, _key: u64) {
+        #[cfg(feature = "change-tracking")]
+        self.inserted_at.remove(&_key);
+
+        #[cfg(feature = "entry-generations")]
+        self.entry_generations.remove(_key);
+
+        #[cfg(feature = "track-removals")]
+        self.removals.record(_key);
+
+        self.generation += 1;
+    }
+
+    fn record_clear(&mut self) {
+        #[cfg(feature = "change-tracking")]
+        self.inserted_at.clear();
+
+        #[cfg(feature = "entry-generations")]
+        self.entry_generations.clear();
+
+        self.slot_holes.clear();
+
+        self.generation += self.count as u64;
+    }
+
+    fn ensure_load_rate(&mut self) {
+        if self.frozen {
+            return;
+        }
+
+        while ((self.count * 100) / self.cache.len()) > self.max_load {
+            self.increase_cache();
+        }
+    }
+
+    fn maybe_shrink(&mut self) {
+        if !self.auto_shrink || self.frozen {
+            return;
+        }
+
+        let min_size = self.min_size;
+        let mut size = self.size;
+        while size > min_size && (self.count * 100) >> size < 15 {
+            size -= 1;
+        }
+
+        if size != self.size {
+            self.rehash_to_size(size);
+        }
+    }
+
+    /// Smallest size whose table holds `count` elements within the load rate `max_load`.
+    fn min_size_for(count: usize, max_load: usize) -> u32 {
+        let mut size = DEFAULT_CAPACITY.trailing_zeros();
+        while (count * 100) >> size > max_load {
+            size += 1;
+        }
+
+        size
+    }
+
+    /// Longest bucket expected when hashing `count` keys uniformly into `buckets` buckets:
+    /// the largest length that at least half a bucket is expected to reach.
+    fn expected_max_bucket_len(count: usize, buckets: usize) -> usize {
+        // Bucket lengths are approximately Poisson distributed with mean `load`.
+        let load = count as f64 / buckets as f64;
+        let mut pmf = (-load).exp();
+        let mut tail = 1.0 - pmf;
+        let mut len = 1;
+        while len < count && buckets as f64 * (tail - pmf * load / len as f64) >= 0.5 {
+            pmf *= load / len as f64;
+            tail -= pmf;
+            len += 1;
+        }
+
+        len
+    }
+
+    #[inline]
+    fn within_load_rate(&self, lim: usize) -> bool {
+        ((self.count * 100) / lim) <= self.max_load
+    }
+
+    /// Number of elements in map.
+    ///
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Force count number of slots filled.
+    ///
+    pub fn load(&self) -> u64 {
+        let mut count = 0;
+
+        for i in 0..self.cache.len() {
+            if self.cache[i].len() > 0 {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Counts the `(empty, singleton, multi)` buckets, i.e. those with no entry, exactly
+    /// one entry and more than one entry, in a single pass without allocating.
+    ///
+    /// The three counts sum up to `capacity()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::deterministic();
+    /// map.insert(0, 1);
+    /// map.insert(1 << 40, 2);
+    ///
+    /// assert_eq!(map.occupancy_summary(), (map.capacity() - 1, 0, 1));
+    /// ```
+    pub fn occupancy_summary(&self) -> (usize, usize, usize) {
+        let mut summary = (0, 0, 0);
+
+        for vals in self.cache.iter() {
+            match vals.len() {
+                0 => summary.0 += 1,
+                1 => summary.1 += 1,
+                _ => summary.2 += 1,
+            }
+        }
+
+        summary
+    }
+
+    /// Counters of lookups, key comparisons and resizes since the map was created.
+    ///
+    /// Only available with the `stats` feature.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> MapStats {
+        self.stats.snapshot()
+    }
+
+    /// Number of entries in the bucket `key` maps to, i.e. the worst case number of key
+    /// comparisons a `get(key)` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// assert_eq!(map.probe_len(21), 0);
+    ///
+    /// map.insert(21, 42);
+    /// assert_eq!(map.probe_len(21), 1);
+    /// ```
+    pub fn probe_len(&self, key: u64) -> usize {
+        self.cache[self.calc_index(key)].len()
+    }
+
+    /// Returns true if both keys are hashed to the same bucket at the current capacity and
+    /// seed, whether or not they are in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = IntMap::new();
+    /// assert!(map.keys_collide(1, 1 + (1 << 40)));
+    /// assert!(!map.keys_collide(1, 2));
+    /// ```
+    pub fn keys_collide(&self, a: u64, b: u64) -> bool {
+        self.calc_index(a) == self.calc_index(b)
+    }
+
+    /// Number of entries in the longest bucket, i.e. the worst case number of key
+    /// comparisons for a lookup.
+    pub fn max_bucket_len(&self) -> usize {
+        self.cache.iter().map(|vals| vals.len()).max().unwrap_or(0)
+    }
+
+    /// Ratio of the longest bucket to the longest bucket expected if the keys were hashed
+    /// uniformly at the current load, or 1.0 for an empty map.
+    ///
+    /// Values near 1.0 mean the hash spreads the keys well. Much larger values mean the
+    /// keys cluster, either by accident or because they were picked to collide; see
+    /// `reseed` and `optimize_for`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let spread: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+    /// assert!(spread.distribution_quality() < 2.0);
+    ///
+    /// let clustered: IntMap<u64> = (0..1_000).map(|i| (i << 32, i)).collect();
+    /// assert!(clustered.distribution_quality() > 10.0);
+    /// ```
+    pub fn distribution_quality(&self) -> f64 {
+        if self.count == 0 {
+            return 1.0;
+        }
+
+        let expected = Self::expected_max_bucket_len(self.count, self.cache.len());
+        self.max_bucket_len() as f64 / expected as f64
+    }
+
+    /// Iterates over `(bucket_index, length)` for every bucket, including empty ones,
+    /// without allocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    ///
+    /// assert_eq!(map.bucket_load_iter().count(), map.capacity());
+    /// assert_eq!(map.bucket_load_iter().map(|(_, len)| len).sum::<usize>(), 100);
+    /// ```
+    pub fn bucket_load_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cache.iter().map(|vals| vals.len()).enumerate()
+    }
+
+    /// Shrinks the storage of one bucket to fit its entries.
+    ///
+    /// Buckets keep the memory of removed entries. Compacting a few buckets at a time, for
+    /// example those `bucket_load_iter` reports as empty, spreads the cost of reclaiming
+    /// it over many calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is not below `capacity()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    /// map.retain(|_, _| false);
+    ///
+    /// for bucket in 0..map.capacity() {
+    ///     map.compact_bucket(bucket);
+    /// }
+    /// assert!(map.raw_buckets().iter().all(|vals| vals.capacity() == 0));
+    /// ```
+    pub fn compact_bucket(&mut self, bucket: usize) {
+        assert!(
+            bucket < self.cache.len(),
+            "bucket {} out of range for capacity {}",
+            bucket,
+            self.cache.len()
+        );
+
+        self.cache[bucket].shrink_to_fit();
+    }
+
+    /// Keys in the longest bucket, i.e. the keys that collide the most under the current
+    /// hash. If several buckets are equally long, the keys of one of them are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// map.insert(1, 1);
+    /// map.insert(1 << 40, 2);
+    /// map.insert(2 << 40, 3);
+    ///
+    /// let mut keys = map.worst_bucket_keys();
+    /// keys.sort();
+    /// assert_eq!(keys, [1 << 40, 2 << 40]);
+    /// ```
+    pub fn worst_bucket_keys(&self) -> Vec<u64> {
+        match self.cache.iter().max_by_key(|vals| vals.len()) {
+            Some(vals) => vals.iter().map(|kv| kv.0).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn load_rate(&self) -> f64 {
+        (self.count as f64) / (self.cache.len() as f64) * 100f64
+    }
+
+    /// Total number of slots available.
+    ///
+    pub fn capacity(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Summarizes the logical and physical size of the map in one call, see
+    /// `CapacityReport`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+    ///
+    /// let report = map.capacity_report();
+    /// assert_eq!(report.count, 100);
+    /// assert_eq!(report.bucket_count, map.capacity());
+    /// assert!(report.total_slot_capacity >= report.count);
+    /// ```
+    pub fn capacity_report(&self) -> CapacityReport {
+        let total_slot_capacity: usize = self.cache.iter().map(|vals| vals.capacity()).sum();
+        let bytes_estimate = std::mem::size_of::<Self>()
+            + self.cache.capacity() * std::mem::size_of::<Vec<(u64, V)>>()
+            + total_slot_capacity * std::mem::size_of::<(u64, V)>();
+
+        CapacityReport {
+            count: self.count,
+            bucket_count: self.cache.len(),
+            total_slot_capacity,
+            bytes_estimate,
+            load_rate: self.load_rate(),
+        }
+    }
+
+    /// Number of elements that can be inserted before the map exceeds its maximum load rate,
+    /// 70% unless set by `with_capacity_and_load`, and grows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::with_capacity(100);
+    /// let capacity = map.capacity();
+    ///
+    /// for i in 0..map.spare_capacity() as u64 {
+    ///     map.insert(i, i);
+    /// }
+    /// assert_eq!(map.spare_capacity(), 0);
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn spare_capacity(&self) -> usize {
+        // The largest count for which `within_load_rate` holds.
+        let max_count = ((self.max_load + 1) * self.cache.len()).saturating_sub(1) / 100;
+        max_count.saturating_sub(self.count)
+    }
+
+    /// Returns true if the map is filled to less than 25% and larger than its minimum
+    /// capacity, i.e. if shrinking it with `resize_to_size` would free memory.
+    ///
+    /// This only compares two counters, so it is cheap enough to poll many maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<u64> = IntMap::new();
+    /// for i in 0..1_000 {
+    ///     map.insert(i, i);
+    /// }
+    /// assert!(!map.should_shrink());
+    ///
+    /// for i in 0..900 {
+    ///     map.remove(i);
+    /// }
+    /// assert!(map.should_shrink());
+    /// ```
+    pub fn should_shrink(&self) -> bool {
+        self.size > self.min_size && (self.count * 100) / self.cache.len() < 25
+    }
+
+    /// The power of two the capacity is made of, `capacity() == 2^size_exponent()`.
+    ///
+    /// Together with `resize_to_size` this allows snapshotting and restoring the shape of a
+    /// map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let map: IntMap<u64> = IntMap::with_capacity(20);
+    /// assert_eq!(map.size_exponent(), 5);
+    /// assert_eq!(map.capacity(), 32);
+    /// ```
+    pub fn size_exponent(&self) -> u32 {
+        self.size
+    }
+
+    pub fn assert_count(&self) -> bool {
+        let mut count = 0;
+
+        for i in 0..self.cache.len() {
+            for _ in self.cache[i].iter() {
+                count += 1;
+            }
+        }
+
+        self.count == count
+    }
+
+    /// Resets the element count to the number of entries actually stored, the value
+    /// `assert_count` compares against.
+    ///
+    /// The count can only get out of sync through leaked guards such as a forgotten
+    /// `Drain`, so this is a defensive repair tool rather than something normal code needs.
+    pub fn recompute_count(&mut self) {
+        self.count = self.cache.iter().map(|vals| vals.len()).sum();
+    }
+
+    pub fn collisions(&self) -> IntMap<u64> {
+        let mut map = IntMap::new();
+
+        for s in self.cache.iter() {
+            let key = s.len() as u64;
+            if key > 1 {
+                if !map.contains_key(key) {
+                    map.insert(key, 1);
+                } else {
+                    let counter = map.get_mut(key).unwrap();
+                    *counter += 1;
+                }
+            }
+        }
+
+        // map.sort();
+
+        map
     }
 
-    /// Remove value from the IntMap.
+    /// Maps every bucket length, including 0, to the number of buckets with that length.
+    ///
+    /// Unlike `collisions` this covers all buckets, so the values sum up to `capacity()`.
     ///
     /// # Examples
     ///
@@ -170,275 +3423,441 @@ impl<V> IntMap<V> {
     ///
     /// let mut map: IntMap<u64> = IntMap::new();
     /// map.insert(21, 42);
-    /// let val = map.remove(21);
-    /// assert!(val.is_some());
-    /// assert_eq!(val.unwrap(), 42);
-    /// assert!(!map.contains_key(21));
+    ///
+    /// let distribution = map.bucket_length_distribution();
+    /// assert_eq!(distribution.get(1), Some(&1));
+    /// assert_eq!(distribution.get(0), Some(&(map.capacity() as u64 - 1)));
     /// ```
-    pub fn remove(&mut self, key: u64) -> Option<V> {
-        let ix = self.calc_index(key);
+    pub fn bucket_length_distribution(&self) -> IntMap<u64> {
+        let mut map = IntMap::new();
 
-        let ref mut vals = self.cache[ix];
+        for vals in self.cache.iter() {
+            *map.entry(vals.len() as u64).or_insert(0) += 1;
+        }
 
-        if vals.len() > 0 {
-            for i in 0..vals.len() {
-                let peek = vals[i].0;
+        map
+    }
+}
 
-                if peek == key {
-                    self.count -= 1;
-                    let kv = vals.swap_remove(i);
-                    return Some(kv.1);
-                }
-            }
+// ***************** Boxed values *********************
 
-            return None;
-        } else {
-            return None;
-        }
+/// Helpers for maps storing large values behind a `Box`.
+///
+/// Buckets then only hold a key and a pointer per entry, which keeps scanning a bucket for
+/// a key cheap no matter how large `V` is. These helpers hide the extra indirection.
+impl<V: ?Sized> IntMap<Box<V>> {
+    /// Get a reference to the boxed value for the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntMap;
+    ///
+    /// let mut map: IntMap<Box<[u8]>> = IntMap::new();
+    /// map.insert(21, vec![0; 4096].into_boxed_slice());
+    ///
+    /// let value: &[u8] = map.get_boxed(21).unwrap();
+    /// assert_eq!(value.len(), 4096);
+    /// ```
+    pub fn get_boxed(&self, key: u64) -> Option<&V> {
+        self.get(key).map(|boxed| &**boxed)
     }
 
-    /// Returns true if key is in map.
+    /// Get a mutable reference to the boxed value for the key.
+    pub fn get_boxed_mut(&mut self, key: u64) -> Option<&mut V> {
+        self.get_mut(key).map(|boxed| &mut **boxed)
+    }
+
+    /// Iterates over all entries, dereferencing the boxed values.
+    pub fn iter_boxed(&self) -> impl Iterator<Item = (u64, &V)> {
+        self.iter().map(|(k, boxed)| (*k, &**boxed))
+    }
+}
+
+impl<V> IntMap<Box<V>> {
+    /// Box `value` and insert it into the IntMap if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    pub fn insert_boxed(&mut self, key: u64, value: V) -> bool {
+        self.insert(key, Box::new(value))
+    }
+
+    /// Remove the value for the key, moving it out of its box.
+    pub fn remove_boxed(&mut self, key: u64) -> Option<V> {
+        self.remove(key).map(|boxed| *boxed)
+    }
+}
+
+/// Helpers for interning strings by integer id.
+impl IntMap<Box<str>> {
+    /// Get the string stored for the key.
     ///
     /// # Examples
     ///
     /// ```
     /// use intmap::IntMap;
     ///
-    /// let mut map: IntMap<u64> = IntMap::new();
-    /// map.insert(21, 42);
-    /// assert!(map.contains_key(21));
+    /// let mut names: IntMap<Box<str>> = IntMap::new();
+    /// names.insert_str(21, "Bart");
+    /// assert_eq!(names.get_str(21), Some("Bart"));
     /// ```
-    pub fn contains_key(&self, key: u64) -> bool {
-        match self.get(key) {
-            Some(_) => true,
-            None => false,
+    pub fn get_str(&self, key: u64) -> Option<&str> {
+        self.get_boxed(key)
+    }
+
+    /// Copy `value` into a new box and insert it if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    pub fn insert_str(&mut self, key: u64, value: &str) -> bool {
+        if self.contains_key(key) {
+            return false;
         }
+
+        self.insert(key, value.into())
     }
+}
 
-    /// Removes all elements from map.
+/// Helpers for two-level maps, such as sparse matrices indexed by row and column.
+impl<V> IntMap<IntMap<V>> {
+    /// Get the value stored for `inner` in the map stored for `outer`.
     ///
     /// # Examples
     ///
     /// ```
     /// use intmap::IntMap;
     ///
-    /// let mut map: IntMap<u64> = IntMap::new();
-    /// map.insert(21, 42);
-    /// map.clear();
-    /// assert_eq!(map.len(), 0);
+    /// let mut matrix: IntMap<IntMap<f64>> = IntMap::new();
+    /// matrix.insert_nested(3, 7, 0.5);
+    ///
+    /// assert_eq!(matrix.get_nested(3, 7), Some(&0.5));
+    /// assert_eq!(matrix.get_nested(3, 8), None);
+    /// assert_eq!(matrix.get_nested(4, 7), None);
     /// ```
-    pub fn clear(&mut self) {
-        for i in 0..self.cache.len() {
-            self.cache[i].clear();
-        }
+    pub fn get_nested(&self, outer: u64, inner: u64) -> Option<&V> {
+        self.get(outer)?.get(inner)
+    }
 
-        self.count = 0;
+    /// Insert `value` for `inner` into the map stored for `outer`, creating that map first
+    /// if there is none.
+    ///
+    /// Like `insert`, this returns true if the value was inserted and false if `inner`
+    /// was already present.
+    pub fn insert_nested(&mut self, outer: u64, inner: u64, value: V) -> bool {
+        self.get_mut_or_else_insert_with(outer, IntMap::new)
+            .insert(inner, value)
     }
+}
 
-    /// Retains only the elements specified by the predicate.
+// ***************** Binary encoding *********************
+
+#[cfg(feature = "binary-io")]
+impl<V: BinaryValue> IntMap<V> {
+    /// Writes all entries to `w`, without building the encoding in memory first.
     ///
-    /// In other words, remove all elements such that `f(key, &value)` returns false.
+    /// The encoding is the number of entries as a little-endian `u64`, followed by every
+    /// key as a little-endian `u64` and its value as encoded by `BinaryValue`. Only
+    /// available with the `binary-io` feature.
     ///
     /// # Examples
     ///
     /// ```
     /// use intmap::IntMap;
     ///
-    /// let mut map: IntMap<u64> = IntMap::new();
-    /// map.insert(1, 11);
-    /// map.insert(2, 12);
-    /// map.insert(4, 13);
+    /// let map: IntMap<u32> = (0..100).map(|i| (i, i as u32 * 2)).collect();
     ///
-    /// // retain only the odd values
-    /// map.retain(|k, v| *v % 2 == 1);
+    /// let mut bytes = Vec::new();
+    /// map.write_to(&mut bytes).unwrap();
     ///
-    /// assert_eq!(map.len(), 2);
-    /// assert!(map.contains_key(1));
-    /// assert!(map.contains_key(4));
+    /// let copy: IntMap<u32> = IntMap::read_from(&mut bytes.as_slice()).unwrap();
+    /// assert_eq!(copy, map);
     /// ```
-    pub fn retain<F>(&mut self, mut f: F)
-    where
-        F: FnMut(u64, &V) -> bool,
-    {
-        let mut removed = 0;
-        for i in 0..self.cache.len() {
-            self.cache[i].retain(|(k, v)| {
-                let keep = (f)(*k, v);
-                if !keep {
-                    removed += 1;
-                }
-                keep
-            });
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(&(self.count as u64).to_le_bytes())?;
+        for (k, v) in self.iter() {
+            w.write_all(&k.to_le_bytes())?;
+            v.encode(w)?;
         }
 
-        self.count -= removed;
+        Ok(())
     }
 
-    /// Returns true if map is empty
+    /// Reads a map written by `write_to` from `r`.
+    ///
+    /// Fails with `UnexpectedEof` if the input ends early, and with `InvalidData` if a
+    /// key occurs more than once. Only available with the `binary-io` feature.
+    pub fn read_from<R: std::io::Read>(r: &mut R) -> std::io::Result<Self> {
+        let count = u64::decode(r)? as usize;
+
+        // The count is untrusted, so don't let a corrupt one allocate the whole memory.
+        let mut map = IntMap::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            let key = u64::decode(r)?;
+            let value = V::decode(r)?;
+            if !map.insert(key, value) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("duplicate key {}", key),
+                ));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+// ***************** Parallel *********************
+
+#[cfg(feature = "rayon")]
+impl<V: Clone + Send + Sync> IntMap<V> {
+    /// Builds a map from `pairs` on the rayon thread pool.
+    ///
+    /// Every thread hashes a chunk of the slice into a partial bucket table of the final
+    /// size, then the partial tables are merged bucket by bucket, again in parallel. Like
+    /// `insert_slice_last_wins`, a key that occurs more than once ends up with the value of
+    /// its last occurrence in the slice, however the work was split. Only available with
+    /// the `rayon` feature.
     ///
     /// # Examples
     ///
     /// ```
     /// use intmap::IntMap;
     ///
-    /// let mut map: IntMap<u64> = IntMap::new();
-    /// map.insert(21, 42);
-    /// assert!(!map.is_empty());
-    /// map.remove(21);
-    /// assert!(map.is_empty());
+    /// let pairs: Vec<(u64, u64)> = (0..10_000).map(|i| (i % 5_000, i)).collect();
+    /// let map = IntMap::par_from_slice(&pairs);
+    ///
+    /// assert_eq!(map.len(), 5_000);
+    /// assert_eq!(map.get(42), Some(&5_042));
     /// ```
-    pub fn is_empty(&mut self) -> bool {
-        self.count == 0
-    }
+    pub fn par_from_slice(pairs: &[(u64, V)]) -> IntMap<V> {
+        use rayon::prelude::*;
 
-    //**** Iterators *****
+        let mut map = IntMap::with_capacity(pairs.len());
+        if pairs.is_empty() {
+            return map;
+        }
 
-    pub fn iter(&self) -> Iter<u64, V> {
-        Iter::new(&self.cache)
-    }
+        let threads = rayon::current_num_threads();
+        let chunk_len = pairs.len().div_ceil(threads);
+        let shape = &map;
+        let parts: Vec<Vec<Vec<(u64, V)>>> = pairs
+            .par_chunks(chunk_len)
+            .map(|chunk| {
+                let mut part: Vec<Vec<(u64, V)>> =
+                    (0..shape.cache.len()).map(|_| Vec::new()).collect();
+                for (k, v) in chunk {
+                    merge_into_bucket(&mut part[shape.calc_index(*k)], *k, v.clone());
+                }
+                part
+            })
+            .collect();
 
-    pub fn iter_mut(&mut self) -> IterMut<u64, V> {
-        IterMut::new(&mut self.cache)
+        // Chunks are merged in slice order, so later occurrences overwrite earlier ones.
+        let mut parts = parts.into_iter();
+        map.cache = parts.next().unwrap();
+        for part in parts {
+            map.cache
+                .par_iter_mut()
+                .zip(part.into_par_iter())
+                .for_each(|(bucket, entries)| {
+                    for (k, v) in entries {
+                        merge_into_bucket(bucket, k, v);
+                    }
+                });
+        }
+
+        map.adopt_entries();
+        map
     }
+}
 
-    pub fn keys(&self) -> Keys<u64, V> {
-        Keys { inner: self.iter() }
+// Callback registered with `IntMap::on_resize`. It cannot be cloned, so clones of a map
+// start without one.
+#[derive(Default)]
+struct ResizeHook(Option<Box<dyn FnMut(usize, usize) + Send + Sync>>);
+
+impl Clone for ResizeHook {
+    fn clone(&self) -> Self {
+        ResizeHook(None)
     }
+}
 
-    pub fn values(&self) -> Values<u64, V> {
-        Values { inner: self.iter() }
+#[inline]
+pub(crate) fn mix(mut x: u64) -> u64 {
+    // Finalizer of splitmix64, every input bit affects every output bit.
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+// Hasher behind `IntMap::content_hash`, its output is part of the documented behavior.
+#[derive(Default)]
+struct StableHasher(u64);
+
+impl std::hash::Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        mix(self.0)
     }
 
-    pub fn values_mut(&mut self) -> ValuesMut<u64, V> {
-        ValuesMut {
-            inner: self.iter_mut(),
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
         }
+        self.write_u64(bytes.len() as u64);
     }
 
-    pub fn drain(&mut self) -> Drain<u64, V> {
-        Drain::new(&mut self.cache, &mut self.count)
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i as u64);
     }
 
-    //**** Internal hash stuff *****
-
-    #[inline]
-    fn hash_u64(seed: u64) -> u64 {
-        let a = 11400714819323198549u64;
-        let val = a.wrapping_mul(seed);
-        val
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
     }
 
-    #[inline]
-    fn calc_index(&self, key: u64) -> usize {
-        let hash = Self::hash_u64(key);
-        // Faster modulus
-        (hash & self.mod_mask) as usize
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
     }
 
-    #[inline]
-    fn lim(&self) -> usize {
-        2u64.pow(self.size) as usize
+    fn write_u64(&mut self, i: u64) {
+        self.0 = mix(self.0 ^ i).wrapping_add(HASH_MULTIPLIER);
     }
 
-    fn increase_cache(&mut self) {
-        self.size += 1;
-        let new_lim = self.lim();
-        self.mod_mask = (new_lim as u64) - 1;
-
-        let mut vec: Vec<Vec<(u64, V)>> = Vec::new();
+    fn write_u128(&mut self, i: u128) {
+        self.write_u64(i as u64);
+        self.write_u64((i >> 64) as u64);
+    }
 
-        vec.append(&mut self.cache);
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
 
-        for _ in 0..new_lim {
-            self.cache.push(Vec::with_capacity(0));
-        }
+    fn write_i8(&mut self, i: i8) {
+        self.write_u64(i as i64 as u64);
+    }
 
-        while vec.len() > 0 {
-            let mut values = vec.pop().unwrap();
-            while values.len() > 0 {
-                if let Some(k) = values.pop() {
-                    let ix = self.calc_index(k.0);
+    fn write_i16(&mut self, i: i16) {
+        self.write_u64(i as i64 as u64);
+    }
 
-                    let ref mut vals = self.cache[ix];
-                    vals.push(k);
-                }
-            }
-        }
+    fn write_i32(&mut self, i: i32) {
+        self.write_u64(i as i64 as u64);
+    }
 
-        debug_assert!(
-            self.cache.len() == self.lim(),
-            "cache vector the wrong length, lim: {:?} cache: {:?}",
-            self.lim(),
-            self.cache.len()
-        );
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
     }
 
-    fn ensure_load_rate(&mut self) {
-        while ((self.count * 100) / self.cache.len()) > 70 {
-            self.increase_cache();
-        }
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
     }
 
-    /// Number of elements in map.
-    ///
-    pub fn len(&self) -> usize {
-        self.count as usize
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
     }
+}
 
-    /// Force count number of slots filled.
-    ///
-    pub fn load(&self) -> u64 {
-        let mut count = 0;
+// Puts an entry into a bucket of `IntMap::par_from_slice`, overwriting the value if the
+// key is already there.
+#[cfg(feature = "rayon")]
+fn merge_into_bucket<V>(bucket: &mut Vec<(u64, V)>, key: u64, value: V) {
+    match bucket.iter_mut().find(|kv| kv.0 == key) {
+        Some(kv) => kv.1 = value,
+        None => bucket.push((key, value)),
+    }
+}
 
-        for i in 0..self.cache.len() {
-            if self.cache[i].len() > 0 {
-                count += 1;
-            }
-        }
+/// Error returned by `IntMap::from_iter_checked` when the keys collide too much.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooClustered {
+    /// Length of the longest bucket with the best seed that was tried.
+    pub max_bucket_len: usize,
+    /// The limit that was exceeded.
+    pub max_chain: usize,
+}
 
-        count
+impl std::fmt::Display for TooClustered {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "keys too clustered: {} entries in one bucket, at most {} allowed",
+            self.max_bucket_len, self.max_chain
+        )
     }
+}
 
-    pub fn load_rate(&self) -> f64 {
-        (self.count as f64) / (self.cache.len() as f64) * 100f64
-    }
+impl std::error::Error for TooClustered {}
 
-    /// Total number of slots available.
-    ///
-    pub fn capacity(&self) -> usize {
-        self.cache.len()
+/// Error returned by `IntMap::from_columns` when the columns differ in length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthMismatch {
+    /// Number of keys.
+    pub keys: usize,
+    /// Number of values.
+    pub values: usize,
+}
+
+impl std::fmt::Display for LengthMismatch {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{} keys but {} values", self.keys, self.values)
     }
+}
 
-    pub fn assert_count(&self) -> bool {
-        let mut count = 0;
+impl std::error::Error for LengthMismatch {}
 
-        for i in 0..self.cache.len() {
-            for _ in self.cache[i].iter() {
-                count += 1;
-            }
-        }
+/// Result of `IntMap::insert_counting`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InsertOutcome<V> {
+    /// The key was not present and has been inserted.
+    Inserted,
+    /// The key was present, its previous value is returned.
+    Replaced(V),
+}
 
-        self.count == count
-    }
+/// Sizing of an `IntMap`, see `IntMap::capacity_report`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CapacityReport {
+    /// Number of entries, as returned by `len`.
+    pub count: usize,
+    /// Number of buckets, as returned by `capacity`.
+    pub bucket_count: usize,
+    /// Number of entries the buckets can hold without reallocating, summed over all
+    /// buckets.
+    pub total_slot_capacity: usize,
+    /// Bytes used by the map and its buckets, not counting memory owned by the values.
+    pub bytes_estimate: usize,
+    /// Entries per bucket in percent, as returned by `load_rate`.
+    pub load_rate: f64,
+}
 
-    pub fn collisions(&self) -> IntMap<u64> {
-        let mut map = IntMap::new();
+/// Map an entry yielded by `IntMap::tagged_iter` comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The key is only present in the map `tagged_iter` was called on.
+    Left,
+    /// The key is only present in the other map.
+    Right,
+    /// The key is present in both maps.
+    Both,
+}
 
-        for s in self.cache.iter() {
-            let key = s.len() as u64;
-            if key > 1 {
-                if !map.contains_key(key) {
-                    map.insert(key, 1);
-                } else {
-                    let counter = map.get_mut(key).unwrap();
-                    *counter += 1;
-                }
-            }
-        }
+/// Handle to the position of an entry inside an `IntMap`, see `IntMap::get_mut_or_slot`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketSlot {
+    bucket: usize,
+    index: usize,
+    key: u64,
+}
 
-        // map.sort();
+impl BucketSlot {
+    /// The key of the entry this handle points to.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
 
-        map
+    /// The index of the bucket the entry is stored in.
+    pub fn bucket(&self) -> usize {
+        self.bucket
     }
 }
 
@@ -680,9 +4099,16 @@ impl<K, V> Iterator for IntoIter<K, V> {
 // ***************** Extend *********************
 
 impl<V> Extend<(u64, V)> for IntMap<V> {
+    /// Extends the map with the entries of `iter`, keeping the first value for every key.
+    ///
+    /// Room for the lower bound of the iterator's `size_hint` is reserved up front, so
+    /// extending with an `ExactSizeIterator` of new keys resizes at most once.
     #[inline]
     fn extend<T: IntoIterator<Item = (u64, V)>>(&mut self, iter: T) {
-        for elem in iter {
+        let iterator = iter.into_iter();
+        self.reserve(iterator.size_hint().0);
+
+        for elem in iterator {
             self.insert(elem.0, elem.1);
         }
     }
@@ -691,6 +4117,12 @@ impl<V> Extend<(u64, V)> for IntMap<V> {
 // ***************** FromIterator *********************
 
 impl<V> std::iter::FromIterator<(u64, V)> for IntMap<V> {
+    /// Collects the entries of `iter` into a new map, keeping the first value for every
+    /// key.
+    ///
+    /// The map is sized for the lower bound of the iterator's `size_hint`, so collecting
+    /// an `ExactSizeIterator` of distinct keys never resizes; longer iterators grow the map
+    /// as usual.
     #[inline]
     fn from_iter<T: IntoIterator<Item = (u64, V)>>(iter: T) -> Self {
         let iterator = iter.into_iter();
@@ -711,7 +4143,7 @@ where
     V: PartialEq,
 {
     fn eq(&self, other: &IntMap<V>) -> bool {
-        self.iter().all(|(k, a)| other.get(*k) == Some(a))
+        self.len() == other.len() && self.iter().all(|(k, a)| other.get(*k) == Some(a))
     }
 }
 impl<V> Eq for IntMap<V> where V: Eq {}
@@ -0,0 +1,56 @@
+use crate::IntMap;
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{
+    FlatMapIter, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+
+type Bucket<V> = Vec<(u64, V)>;
+type BucketValuesMut<'a, V> =
+    std::iter::Map<std::slice::IterMut<'a, (u64, V)>, fn(&'a mut (u64, V)) -> &'a mut V>;
+type ToBucketValuesMut<'a, V> = fn(&'a mut Bucket<V>) -> BucketValuesMut<'a, V>;
+
+/// Parallel iterator over mutable references to the values of an `IntMap`.
+///
+/// Created by `IntoParallelIterator` for `&mut IntMap<V>`. Only available with the `rayon`
+/// feature.
+pub struct ParValuesMut<'a, V: Send> {
+    // Rayon splits the bucket table into disjoint ranges, and every bucket is only walked
+    // by the thread that got it, so no two references ever alias.
+    inner: FlatMapIter<rayon::slice::IterMut<'a, Bucket<V>>, ToBucketValuesMut<'a, V>>,
+}
+
+/// Iterates over the values in parallel, in no particular order.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::IntMap;
+/// use rayon::prelude::*;
+///
+/// let mut map: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+/// (&mut map).into_par_iter().for_each(|v| *v *= 2);
+///
+/// assert_eq!(map.get(21), Some(&42));
+/// ```
+impl<'a, V: Send> IntoParallelIterator for &'a mut IntMap<V> {
+    type Item = &'a mut V;
+    type Iter = ParValuesMut<'a, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        fn bucket_values<V>(bucket: &mut Bucket<V>) -> BucketValuesMut<'_, V> {
+            bucket.iter_mut().map(|kv| &mut kv.1)
+        }
+
+        ParValuesMut {
+            inner: self.cache.par_iter_mut().flat_map_iter(bucket_values),
+        }
+    }
+}
+
+impl<'a, V: Send> ParallelIterator for ParValuesMut<'a, V> {
+    type Item = &'a mut V;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.inner.drive_unindexed(consumer)
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{mix, HASH_MULTIPLIER};
+
+// Average number of keys per displacement bucket, larger values give a smaller
+// displacement table but make placing the last buckets harder.
+const KEYS_PER_BUCKET: usize = 4;
+
+// Displacements tried for a single bucket before giving up on the current seed.
+const MAX_DISPLACEMENT: u32 = 1 << 16;
+
+// Seeds tried before construction fails.
+const MAX_ATTEMPTS: u64 = 8;
+
+/// A read-only map for a fixed set of `u64` keys where every lookup compares exactly one
+/// key.
+///
+/// `from_keys` computes a collision-free placement of all keys with the
+/// compress-hash-displace (CHD) algorithm: keys are first hashed into small buckets, and
+/// each bucket then gets a displacement picking a hash that moves all its keys to free
+/// slots. A lookup hashes the key to its bucket, reads the bucket's displacement and
+/// checks the single slot that yields. Construction is more expensive than building an
+/// `IntMap`, so this suits static lookup tables that are built once and queried often.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::PerfectIntMap;
+///
+/// let map = PerfectIntMap::from_keys(&[1, 2, 3], |k| k * 10).unwrap();
+/// assert_eq!(map.get(2), Some(&20));
+/// assert_eq!(map.get(4), None);
+/// ```
+pub struct PerfectIntMap<V> {
+    slots: Vec<Option<(u64, V)>>,
+    displacements: Vec<u32>,
+    mod_mask: u64,
+    seed: u64,
+    count: usize,
+    #[cfg(feature = "stats")]
+    stats: crate::stats::StatsCounters,
+}
+
+/// Error returned by `PerfectIntMap::from_keys`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerfectHashError {
+    /// The key occurs more than once.
+    DuplicateKey(u64),
+    /// No collision-free placement was found, which is extremely unlikely for distinct
+    /// keys.
+    NoPlacement,
+}
+
+impl std::fmt::Display for PerfectHashError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PerfectHashError::DuplicateKey(key) => write!(fmt, "duplicate key {}", key),
+            PerfectHashError::NoPlacement => write!(fmt, "no collision-free placement found"),
+        }
+    }
+}
+
+impl std::error::Error for PerfectHashError {}
+
+impl<V> PerfectIntMap<V> {
+    /// Builds a map holding `value(key)` for every key of `keys`.
+    ///
+    /// Fails if a key occurs more than once, or if no placement is found after trying a
+    /// few seeds.
+    pub fn from_keys<F: FnMut(u64) -> V>(
+        keys: &[u64],
+        mut value: F,
+    ) -> Result<Self, PerfectHashError> {
+        let mut sorted = keys.to_vec();
+        sorted.sort_unstable();
+        if let Some(w) = sorted.windows(2).find(|w| w[0] == w[1]) {
+            return Err(PerfectHashError::DuplicateKey(w[0]));
+        }
+
+        let lim = (keys.len() + keys.len() / 4).max(1).next_power_of_two();
+        let mod_mask = (lim as u64) - 1;
+        let bucket_count = (keys.len() / KEYS_PER_BUCKET).max(1);
+
+        let mut seed = 0;
+        let positions = loop {
+            if seed == MAX_ATTEMPTS {
+                return Err(PerfectHashError::NoPlacement);
+            }
+            seed += 1;
+
+            if let Some(placement) = place(keys, seed, mod_mask, bucket_count) {
+                break placement;
+            }
+        };
+        let (displacements, positions) = positions;
+
+        let mut slots: Vec<Option<(u64, V)>> = (0..lim).map(|_| None).collect();
+        for (key, ix) in keys.iter().zip(positions) {
+            slots[ix] = Some((*key, value(*key)));
+        }
+
+        Ok(PerfectIntMap {
+            slots,
+            displacements,
+            mod_mask,
+            seed,
+            count: keys.len(),
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
+        })
+    }
+
+    /// Get value from the PerfectIntMap.
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let bucket = bucket_of(key, self.seed, self.displacements.len());
+        let ix = slot_of(key, self.seed, self.displacements[bucket], self.mod_mask);
+
+        match &self.slots[ix] {
+            Some((k, v)) => {
+                #[cfg(feature = "stats")]
+                self.stats.record_get(1);
+
+                if *k == key {
+                    Some(v)
+                } else {
+                    None
+                }
+            }
+            None => {
+                #[cfg(feature = "stats")]
+                self.stats.record_get(0);
+
+                None
+            }
+        }
+    }
+
+    /// Returns true if key is in map.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of elements in map.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Total number of slots, always a power of two of at least `len() * 1.25`.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &V)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|kv| (kv.0, &kv.1)))
+    }
+
+    /// Counters of lookups and key comparisons since the map was built.
+    ///
+    /// Only available with the `stats` feature.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> crate::MapStats {
+        self.stats.snapshot()
+    }
+}
+
+impl<V> std::fmt::Debug for PerfectIntMap<V>
+where
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Finds a displacement for every bucket, returning them together with the slot of
+/// every key, or `None` if some bucket cannot be placed with this seed.
+fn place(
+    keys: &[u64],
+    seed: u64,
+    mod_mask: u64,
+    bucket_count: usize,
+) -> Option<(Vec<u32>, Vec<usize>)> {
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); bucket_count];
+    for (i, key) in keys.iter().enumerate() {
+        buckets[bucket_of(*key, seed, bucket_count)].push(i);
+    }
+
+    // Place the largest buckets first, while most slots are still free.
+    let mut order: Vec<usize> = (0..bucket_count).collect();
+    order.sort_unstable_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+    let mut taken = vec![false; (mod_mask + 1) as usize];
+    let mut displacements = vec![0; bucket_count];
+    let mut positions = vec![0; keys.len()];
+    let mut candidate = Vec::new();
+
+    for b in order {
+        if buckets[b].is_empty() {
+            break;
+        }
+
+        let found = (0..MAX_DISPLACEMENT).find(|d| {
+            candidate.clear();
+            for i in buckets[b].iter() {
+                let ix = slot_of(keys[*i], seed, *d, mod_mask);
+                if taken[ix] || candidate.contains(&ix) {
+                    return false;
+                }
+                candidate.push(ix);
+            }
+            true
+        })?;
+
+        displacements[b] = found;
+        for (i, ix) in buckets[b].iter().zip(candidate.iter()) {
+            taken[*ix] = true;
+            positions[*i] = *ix;
+        }
+    }
+
+    Some((displacements, positions))
+}
+
+#[inline]
+fn bucket_of(key: u64, seed: u64, bucket_count: usize) -> usize {
+    let hash = mix(key ^ seed.wrapping_mul(HASH_MULTIPLIER));
+    ((hash as u128 * bucket_count as u128) >> 64) as usize
+}
+
+#[inline]
+fn slot_of(key: u64, seed: u64, displacement: u32, mod_mask: u64) -> usize {
+    let salt = (seed << 32 | displacement as u64).wrapping_mul(HASH_MULTIPLIER);
+    (mix(key.wrapping_add(salt)) & mod_mask) as usize
+}
@@ -0,0 +1,42 @@
+// Number of removed keys a map remembers unless created with `IntMap::with_removal_history`.
+pub(crate) const DEFAULT_REMOVAL_HISTORY: usize = 16;
+
+// The most recently removed keys, oldest first, see `IntMap::recent_removals`.
+#[derive(Clone)]
+pub(crate) struct RemovalLog {
+    keys: Vec<u64>,
+    capacity: usize,
+    // Position of the oldest key once the history is full, where the next key goes.
+    head: usize,
+}
+
+impl RemovalLog {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        RemovalLog {
+            keys: Vec::with_capacity(capacity),
+            capacity,
+            head: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record(&mut self, key: u64) {
+        if self.keys.len() < self.capacity {
+            self.keys.push(key);
+        } else if self.capacity > 0 {
+            self.keys[self.head] = key;
+            self.head = (self.head + 1) % self.capacity;
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let (newer, older) = self.keys.split_at(self.head);
+        older.iter().chain(newer).copied()
+    }
+}
+
+impl Default for RemovalLog {
+    fn default() -> Self {
+        RemovalLog::with_capacity(DEFAULT_REMOVAL_HISTORY)
+    }
+}
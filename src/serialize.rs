@@ -0,0 +1,210 @@
+use crate::IntMap;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+#[cfg(feature = "serialize-config")]
+use serde::de::{SeqAccess, Unexpected};
+#[cfg(feature = "serialize-config")]
+use serde::ser::SerializeStruct;
+
+/// Serializes the entries as a map from `u64` keys to values.
+///
+/// With the `serialize-config` feature the map is written as a struct instead, holding
+/// the `seed` and `max_load` of the map next to its `entries`, so that a deserialized
+/// map hashes keys into the same buckets and grows at the same load rate.
+impl<V: Serialize> Serialize for IntMap<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[cfg(feature = "serialize-config")]
+        {
+            let mut s = serializer.serialize_struct("IntMap", FIELDS.len())?;
+            s.serialize_field("seed", &self.seed)?;
+            s.serialize_field("max_load", &(self.max_load as u8))?;
+            s.serialize_field("entries", &Entries(self))?;
+            s.end()
+        }
+
+        #[cfg(not(feature = "serialize-config"))]
+        Entries(self).serialize(serializer)
+    }
+}
+
+/// Deserializes a map written by `Serialize`, failing if a key occurs more than once.
+///
+/// Values may borrow from the input, so an `IntMap<&str>` read from a buffer points into
+/// that buffer instead of allocating a copy of every string.
+///
+/// With the `serialize-config` feature, a missing `seed` or `max_load` falls back to the
+/// defaults of `IntMap::new`.
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for IntMap<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[cfg(feature = "serialize-config")]
+        {
+            deserializer.deserialize_struct("IntMap", FIELDS, ConfigVisitor(PhantomData))
+        }
+
+        #[cfg(not(feature = "serialize-config"))]
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+// The entries of a map on their own, without its configuration.
+struct Entries<'a, V>(&'a IntMap<V>);
+
+impl<V: Serialize> Serialize for Entries<'_, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Compact formats need the length up front, which `Iter` does not report.
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.0.iter() {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+struct EntriesVisitor<V>(PhantomData<V>);
+
+impl<'de, V: Deserialize<'de>> Visitor<'de> for EntriesVisitor<V> {
+    type Value = IntMap<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map with u64 keys")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        // The size hint is untrusted, so don't let a corrupt one allocate the whole memory.
+        let mut map = IntMap::with_capacity(access.size_hint().unwrap_or(0).min(1 << 16));
+        while let Some((key, value)) = access.next_entry::<u64, V>()? {
+            if !map.insert(key, value) {
+                return Err(de::Error::custom(format_args!("duplicate key {}", key)));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serialize-config")]
+const FIELDS: &[&str] = &["seed", "max_load", "entries"];
+
+#[cfg(feature = "serialize-config")]
+enum Field {
+    Seed,
+    MaxLoad,
+    Entries,
+}
+
+#[cfg(feature = "serialize-config")]
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldVisitor;
+
+        impl<'de> Visitor<'de> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("`seed`, `max_load` or `entries`")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Field, E> {
+                match name {
+                    "seed" => Ok(Field::Seed),
+                    "max_load" => Ok(Field::MaxLoad),
+                    "entries" => Ok(Field::Entries),
+                    _ => Err(de::Error::unknown_field(name, FIELDS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+// Deserializes the `entries` field through `EntriesVisitor`.
+#[cfg(feature = "serialize-config")]
+struct EntriesField<V>(IntMap<V>);
+
+#[cfg(feature = "serialize-config")]
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for EntriesField<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(EntriesVisitor(PhantomData))
+            .map(EntriesField)
+    }
+}
+
+#[cfg(feature = "serialize-config")]
+struct ConfigVisitor<V>(PhantomData<V>);
+
+#[cfg(feature = "serialize-config")]
+impl<V> ConfigVisitor<V> {
+    fn build<E: de::Error>(
+        mut map: IntMap<V>,
+        seed: Option<u64>,
+        max_load: Option<u8>,
+    ) -> Result<IntMap<V>, E> {
+        if let Some(max_load) = max_load {
+            if !(1..=100).contains(&max_load) {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(max_load as u64),
+                    &"a load rate within 1 to 100",
+                ));
+            }
+            map.max_load = max_load as usize;
+            map.ensure_load_rate();
+        }
+
+        if let Some(seed) = seed {
+            if seed != map.seed {
+                map.reseed(seed);
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serialize-config")]
+impl<'de, V: Deserialize<'de>> Visitor<'de> for ConfigVisitor<V> {
+    type Value = IntMap<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("struct IntMap")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let seed = access
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let max_load = access
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let entries: EntriesField<V> = access
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+
+        ConfigVisitor::build(entries.0, Some(seed), Some(max_load))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut seed = None;
+        let mut max_load = None;
+        let mut entries = None;
+        while let Some(field) = access.next_key()? {
+            match field {
+                Field::Seed if seed.is_none() => seed = Some(access.next_value()?),
+                Field::MaxLoad if max_load.is_none() => max_load = Some(access.next_value()?),
+                Field::Entries if entries.is_none() => {
+                    entries = Some(access.next_value::<EntriesField<V>>()?.0)
+                }
+                Field::Seed => return Err(de::Error::duplicate_field("seed")),
+                Field::MaxLoad => return Err(de::Error::duplicate_field("max_load")),
+                Field::Entries => return Err(de::Error::duplicate_field("entries")),
+            }
+        }
+
+        let entries = entries.ok_or_else(|| de::Error::missing_field("entries"))?;
+        ConfigVisitor::build(entries, seed, max_load)
+    }
+}
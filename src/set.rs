@@ -0,0 +1,171 @@
+use crate::IntMap;
+
+/// A set of `u64` values, backed by an `IntMap<()>`.
+///
+/// Since `()` is zero-sized, every entry only takes the space of its key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IntSet {
+    inner: IntMap<()>,
+}
+
+impl IntSet {
+    /// Creates a new IntSet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntSet;
+    ///
+    /// let mut set = IntSet::new();
+    /// ```
+    pub fn new() -> Self {
+        IntSet {
+            inner: IntMap::new(),
+        }
+    }
+
+    /// Creates a new IntSet that can hold at least `capacity` values without resizing.
+    pub fn with_capacity(capacity: usize) -> Self {
+        IntSet {
+            inner: IntMap::with_capacity(capacity),
+        }
+    }
+
+    /// Insert value into the IntSet.
+    ///
+    /// This function returns true if the value was not yet present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntSet;
+    ///
+    /// let mut set = IntSet::new();
+    /// assert!(set.insert(21));
+    /// assert!(!set.insert(21));
+    /// ```
+    pub fn insert(&mut self, value: u64) -> bool {
+        self.inner.insert(value, ())
+    }
+
+    /// Returns true if value is in the set.
+    pub fn contains(&self, value: u64) -> bool {
+        self.inner.contains_key(value)
+    }
+
+    /// Remove value from the IntSet, returns true if it was present.
+    pub fn remove(&mut self, value: u64) -> bool {
+        self.inner.remove(value).is_some()
+    }
+
+    /// Removes all values from the set.
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Number of values in the set.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    /// Iterates over all values in the set.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.inner.keys().copied()
+    }
+
+    /// Iterates over the values that are in `self` or `other`, without duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntSet;
+    ///
+    /// let a: IntSet = [1, 2].iter().copied().collect();
+    /// let b: IntSet = [2, 3].iter().copied().collect();
+    ///
+    /// let mut union: Vec<u64> = a.union(&b).collect();
+    /// union.sort();
+    /// assert_eq!(union, [1, 2, 3]);
+    /// ```
+    pub fn union<'a>(&'a self, other: &'a IntSet) -> impl Iterator<Item = u64> + 'a {
+        self.iter()
+            .chain(other.iter().filter(move |v| !self.contains(*v)))
+    }
+
+    /// Iterates over the values that are in both `self` and `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntSet;
+    ///
+    /// let a: IntSet = [1, 2].iter().copied().collect();
+    /// let b: IntSet = [2, 3].iter().copied().collect();
+    ///
+    /// assert_eq!(a.intersection(&b).collect::<Vec<_>>(), [2]);
+    /// ```
+    pub fn intersection<'a>(&'a self, other: &'a IntSet) -> impl Iterator<Item = u64> + 'a {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        small.iter().filter(move |v| large.contains(*v))
+    }
+
+    /// Iterates over the values that are in `self` but not in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use intmap::IntSet;
+    ///
+    /// let a: IntSet = [1, 2].iter().copied().collect();
+    /// let b: IntSet = [2, 3].iter().copied().collect();
+    ///
+    /// assert_eq!(a.difference(&b).collect::<Vec<_>>(), [1]);
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a IntSet) -> impl Iterator<Item = u64> + 'a {
+        self.iter().filter(move |v| !other.contains(*v))
+    }
+}
+
+impl Default for IntSet {
+    fn default() -> Self {
+        IntSet::new()
+    }
+}
+
+// ***************** Extend *********************
+
+impl Extend<u64> for IntSet {
+    #[inline]
+    fn extend<T: IntoIterator<Item = u64>>(&mut self, iter: T) {
+        self.inner.extend(iter.into_iter().map(|v| (v, ())));
+    }
+}
+
+// ***************** FromIterator *********************
+
+impl std::iter::FromIterator<u64> for IntSet {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = u64>>(iter: T) -> Self {
+        IntSet {
+            inner: iter.into_iter().map(|v| (v, ())).collect(),
+        }
+    }
+}
+
+// ***************** Debug *********************
+
+impl std::fmt::Debug for IntSet {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_set().entries(self.iter()).finish()
+    }
+}
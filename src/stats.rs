@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Snapshot of the counters maintained with the `stats` feature, see `IntMap::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapStats {
+    /// Number of lookups through `get` and `contains_key`.
+    pub gets: u64,
+    /// Number of key comparisons done by those lookups.
+    pub comparisons: u64,
+    /// Number of times the bucket table was rebuilt.
+    pub resizes: u64,
+    /// Number of `BucketSlot` lookups that did not find the entry at its recorded position
+    /// and had to scan the bucket.
+    pub slot_scans: u64,
+}
+
+impl MapStats {
+    /// Average number of key comparisons per lookup.
+    pub fn comparisons_per_get(&self) -> f64 {
+        if self.gets == 0 {
+            0.0
+        } else {
+            self.comparisons as f64 / self.gets as f64
+        }
+    }
+}
+
+// Lookups only have `&self`, atomics keep the map `Sync`.
+#[derive(Default)]
+pub(crate) struct StatsCounters {
+    gets: AtomicU64,
+    comparisons: AtomicU64,
+    resizes: AtomicU64,
+    slot_scans: AtomicU64,
+}
+
+impl StatsCounters {
+    #[inline]
+    pub(crate) fn record_get(&self, comparisons: usize) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        self.comparisons
+            .fetch_add(comparisons as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_slot_scan(&self) {
+        self.slot_scans.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_resize(&mut self) {
+        *self.resizes.get_mut() += 1;
+    }
+
+    pub(crate) fn snapshot(&self) -> MapStats {
+        MapStats {
+            gets: self.gets.load(Ordering::Relaxed),
+            comparisons: self.comparisons.load(Ordering::Relaxed),
+            resizes: self.resizes.load(Ordering::Relaxed),
+            slot_scans: self.slot_scans.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Clone for StatsCounters {
+    fn clone(&self) -> Self {
+        let stats = self.snapshot();
+        StatsCounters {
+            gets: AtomicU64::new(stats.gets),
+            comparisons: AtomicU64::new(stats.comparisons),
+            resizes: AtomicU64::new(stats.resizes),
+            slot_scans: AtomicU64::new(stats.slot_scans),
+        }
+    }
+}
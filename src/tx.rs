@@ -0,0 +1,111 @@
+use crate::IntMap;
+
+/// A transaction over an `IntMap` that can undo its inserts.
+///
+/// This is constructed by `IntMap::begin_tx`. Writes go through the guard, which logs the
+/// inserted keys and any values they overwrote. `rollback` undoes exactly those writes,
+/// `commit` keeps them and forgets the log. Dropping the guard without calling either rolls
+/// back, so a panic or early return inside a transaction leaves the map unchanged.
+///
+/// The rest of the map is only readable through the guard, which dereferences to the map.
+///
+/// # Examples
+///
+/// ```
+/// use intmap::IntMap;
+///
+/// let mut map: IntMap<u64> = IntMap::new();
+/// map.insert(1, 10);
+///
+/// let mut tx = map.begin_tx();
+/// tx.insert(2, 20);
+/// tx.replace(1, 11);
+/// assert_eq!(tx.get(1), Some(&11));
+/// tx.rollback();
+///
+/// assert_eq!(map.get(1), Some(&10));
+/// assert!(!map.contains_key(2));
+/// ```
+pub struct TxGuard<'a, V: 'a> {
+    int_map: &'a mut IntMap<V>,
+    // Written keys in order, with the value they replaced if they were present.
+    log: Vec<(u64, Option<V>)>,
+}
+
+impl<'a, V> TxGuard<'a, V> {
+    pub(crate) fn new(int_map: &'a mut IntMap<V>) -> Self {
+        TxGuard {
+            int_map,
+            log: Vec::new(),
+        }
+    }
+
+    /// Insert key/value into the IntMap if the key is not yet inserted.
+    ///
+    /// This function returns true if key/value were inserted and false otherwise.
+    pub fn insert(&mut self, key: u64, value: V) -> bool {
+        let inserted = self.int_map.insert(key, value);
+        if inserted {
+            self.log.push((key, None));
+        }
+
+        inserted
+    }
+
+    /// Insert key/value into the IntMap, overwriting any previous value.
+    ///
+    /// Returns true if a previous value was overwritten. It is kept in the log so
+    /// `rollback` can restore it.
+    pub fn replace(&mut self, key: u64, value: V) -> bool {
+        let old = match self.int_map.get_mut(key) {
+            Some(slot) => Some(std::mem::replace(slot, value)),
+            None => {
+                self.int_map.insert(key, value);
+                None
+            }
+        };
+
+        let replaced = old.is_some();
+        self.log.push((key, old));
+        replaced
+    }
+
+    /// Keeps all writes made in the transaction.
+    pub fn commit(mut self) {
+        self.log.clear();
+    }
+
+    /// Undoes all writes made in the transaction, newest first.
+    pub fn rollback(mut self) {
+        self.undo();
+    }
+
+    fn undo(&mut self) {
+        while let Some((key, old)) = self.log.pop() {
+            match old {
+                Some(value) => {
+                    if let Some(slot) = self.int_map.get_mut(key) {
+                        *slot = value;
+                    }
+                }
+                None => {
+                    self.int_map.remove(key);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, V> std::ops::Deref for TxGuard<'a, V> {
+    type Target = IntMap<V>;
+
+    fn deref(&self) -> &IntMap<V> {
+        self.int_map
+    }
+}
+
+impl<'a, V> Drop for TxGuard<'a, V> {
+    fn drop(&mut self) {
+        self.undo();
+    }
+}
@@ -0,0 +1,114 @@
+extern crate intmap;
+
+use intmap::{ArenaIntMap, IntMap};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_insert_get_remove() {
+        let count = 20_000;
+        let mut map: ArenaIntMap<u64> = ArenaIntMap::new();
+
+        for i in 0..count {
+            assert!(map.insert(i, i));
+        }
+        assert!(!map.insert(0, 1));
+        assert_eq!(map.len(), count as usize);
+
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+        *map.get_mut(7).unwrap() += 1;
+        assert_eq!(map.get(7), Some(&8));
+
+        for i in 0..count {
+            let expected = if i == 7 { 8 } else { i };
+            assert_eq!(map.remove(i), Some(expected));
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.remove(1), None);
+    }
+
+    #[test]
+    fn arena_reuses_removed_slots() {
+        let mut map: ArenaIntMap<u64> = (0..100).map(|i| (i, i)).collect();
+
+        for i in (0..100).step_by(2) {
+            map.remove(i);
+        }
+        for i in 100..150 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.len(), 100);
+
+        // The new keys filled the holes, so the arena did not grow.
+        let mut keys: Vec<u64> = map.iter().map(|kv| kv.0).collect();
+        assert_eq!(keys.len(), 100);
+        keys.sort();
+        let expected: Vec<u64> = (1..100).step_by(2).chain(100..150).collect();
+        assert_eq!(keys, expected);
+
+        for (k, v) in map.iter_mut() {
+            *v = k * 2;
+        }
+        for k in expected {
+            assert_eq!(map.get(k), Some(&(k * 2)));
+        }
+    }
+
+    #[test]
+    fn arena_compacts_after_removals() {
+        let keys: Vec<u64> = (0..1_000).map(|i| (i * 7919) % 1_000).collect();
+        let mut map: ArenaIntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+
+        // Removing most keys compacts the arena, survivors keep their order.
+        for k in keys.iter().filter(|k| *k % 10 != 0) {
+            assert_eq!(map.remove(*k), Some(*k));
+        }
+        let expected: Vec<u64> = keys.iter().copied().filter(|k| k % 10 == 0).collect();
+        assert_eq!(map.len(), expected.len());
+        assert_eq!(map.iter().map(|kv| kv.0).collect::<Vec<_>>(), expected);
+        for k in expected.iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+
+        // Churn keeps lookups and removals consistent across compactions.
+        for round in 1..20 {
+            for k in 0..1_000 {
+                map.insert(round * 1_000 + k, k);
+            }
+            for k in 0..1_000 {
+                assert_eq!(map.remove(round * 1_000 + k), Some(k));
+            }
+        }
+        assert_eq!(map.iter().map(|kv| kv.0).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn arena_iterates_in_insertion_order() {
+        let keys: Vec<u64> = (0..1_000).map(|i| (i * 7919) % 1_000).collect();
+        let map: ArenaIntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+
+        let visited: Vec<u64> = map.iter().map(|kv| kv.0).collect();
+        assert_eq!(visited, keys);
+        assert_eq!(map.values().sum::<u64>(), keys.iter().sum());
+    }
+
+    #[test]
+    fn arena_from_int_map() {
+        let map: IntMap<u64> = (0..1_000).map(|i| (i, i * 3)).collect();
+        let mut arena = ArenaIntMap::from(map.clone());
+
+        assert_eq!(arena.len(), map.len());
+        for (k, v) in map.iter() {
+            assert_eq!(arena.get(*k), Some(v));
+        }
+
+        arena.clear();
+        assert!(arena.is_empty());
+        assert_eq!(arena.iter().count(), 0);
+        assert!(arena.insert(1, 1));
+    }
+}
@@ -2,7 +2,7 @@ extern crate rand;
 
 extern crate intmap;
 
-use intmap::IntMap;
+use intmap::{CapacityReport, Entry, InsertOutcome, IntMap, LengthMismatch, Origin, TooClustered};
 
 #[cfg(test)]
 mod tests {
@@ -271,5 +271,2882 @@ mod tests {
         let map_2 = (0..count).rev().map(|i| (i, i * i)).collect::<IntMap<_>>();
 
         assert_eq!(map_1, map_2);
+
+        let map_3 = (0..count - 1).map(|i| (i, i * i)).collect::<IntMap<_>>();
+        assert_ne!(map_1, map_3);
+        assert_ne!(map_3, map_1);
+    }
+
+    #[test]
+    fn map_is_disjoint() {
+        let map_1 = (0..100).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2 = (100..150).map(|i| (i, i)).collect::<IntMap<u64>>();
+
+        assert!(map_1.is_disjoint(&map_2));
+        assert!(map_2.is_disjoint(&map_1));
+    }
+
+    #[test]
+    fn map_is_disjoint_overlapping() {
+        let map_1 = (0..100).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2 = (99..150)
+            .map(|i| (i, format!("{}", i)))
+            .collect::<IntMap<String>>();
+
+        assert!(!map_1.is_disjoint(&map_2));
+        assert!(!map_2.is_disjoint(&map_1));
+    }
+
+    #[test]
+    fn map_is_disjoint_empty() {
+        let map_1 = (0..100).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2: IntMap<u64> = IntMap::new();
+
+        assert!(map_1.is_disjoint(&map_2));
+        assert!(map_2.is_disjoint(&map_1));
+        assert!(map_2.is_disjoint(&map_2));
+    }
+
+    #[test]
+    fn map_is_key_subset_proper() {
+        let map_1 = (10..50).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2 = (0..100)
+            .map(|i| (i, format!("{}", i)))
+            .collect::<IntMap<String>>();
+
+        assert!(map_1.is_key_subset(&map_2));
+        assert!(!map_2.is_key_subset(&map_1));
+    }
+
+    #[test]
+    fn map_is_key_subset_equal() {
+        let map_1 = (0..100).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2 = (0..100).rev().map(|i| (i, i * 2)).collect::<IntMap<u64>>();
+
+        assert!(map_1.is_key_subset(&map_2));
+        assert!(map_2.is_key_subset(&map_1));
+    }
+
+    #[test]
+    fn map_is_key_subset_not_subset() {
+        let map_1 = (0..10).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map_2 = (5..100).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let empty: IntMap<u64> = IntMap::new();
+
+        assert!(!map_1.is_key_subset(&map_2));
+        assert!(empty.is_key_subset(&map_1));
+        assert!(!map_1.is_key_subset(&empty));
+    }
+
+    #[test]
+    fn map_resize_to_size() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        assert!(map.resize_to_size(16));
+        assert_eq!(map.capacity(), 1 << 16);
+        assert_eq!(map.len(), count as usize);
+        assert!(map.assert_count());
+
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        assert!(map.resize_to_size(11));
+        assert_eq!(map.capacity(), 1 << 11);
+        assert_eq!(map.len(), count as usize);
+
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        // New inserts must land in the buckets lookups probe.
+        for i in count..(count + 100) {
+            assert!(map.insert(i, i));
+            assert_eq!(map.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_resize_to_size_rejects_overload() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        let capacity = map.capacity();
+        assert!(!map.resize_to_size(10));
+        assert!(!map.resize_to_size(64));
+        assert_eq!(map.capacity(), capacity);
+
+        // Tables too large for the address space are refused rather than aborting.
+        assert!(!map.resize_to_size(54));
+        assert!(!map.resize_to_size(usize::BITS - 1));
+        assert_eq!(map.capacity(), capacity);
+
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_send_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<IntMap<u64>>();
+        assert_sync::<IntMap<u64>>();
+        assert_send::<IntMap<String>>();
+        assert_sync::<IntMap<String>>();
+    }
+
+    #[test]
+    fn map_concurrent_reads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let count = 10_000;
+        let map = Arc::new((0..count).map(|i| (i, i * 2)).collect::<IntMap<u64>>());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for i in 0..count {
+                        assert_eq!(map.get(i), Some(&(i * 2)));
+                    }
+                    map.iter().count()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), count as usize);
+        }
+    }
+
+    #[test]
+    fn map_get_mut_or_slot() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        let (val, slot) = map.get_mut_or_slot(500);
+        *val.unwrap() += 1;
+        let slot = slot.unwrap();
+        assert_eq!(slot.key(), 500);
+
+        // Cached slot keeps working while the map is structurally unchanged.
+        *map.get_mut_by_slot(slot).unwrap() += 1;
+        assert_eq!(map.get_by_slot(slot), Some(&502));
+        assert_eq!(map.get(500), Some(&502));
+
+        assert_eq!(map.get_mut_or_slot(count + 1), (None, None));
+    }
+
+    #[test]
+    fn map_get_by_slot_invalidated() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 1);
+
+        let slot = map.get_mut_or_slot(1).1.unwrap();
+        map.remove(1);
+        assert_eq!(map.get_by_slot(slot), None);
+
+        // Resizing moves the entry, the stale handle must not yield a wrong entry.
+        map.insert(1, 1);
+        let slot = map.get_mut_or_slot(1).1.unwrap();
+        for i in 2..1_000 {
+            map.insert(i, i);
+        }
+        if let Some(val) = map.get_by_slot(slot) {
+            assert_eq!(*val, 1);
+        }
+    }
+
+    #[test]
+    fn map_iter_with_bucket() {
+        let count = 5_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        let entries: Vec<(usize, u64)> = map.iter_with_bucket().map(|(b, k, _)| (b, k)).collect();
+        assert_eq!(entries.len(), count as usize);
+
+        for (bucket, key) in entries {
+            let slot = map.get_mut_or_slot(key).1.unwrap();
+            assert_eq!(bucket, slot.bucket());
+        }
+
+        for (_, key, value) in map.iter_with_bucket() {
+            assert_eq!(key, *value);
+        }
+    }
+
+    #[test]
+    fn map_entry_or_insert_with_key() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            assert_eq!(*map.entry(i).or_insert_with_key(|k| k * 2), i * 2);
+        }
+        assert_eq!(map.len(), count as usize);
+        assert!(map.assert_count());
+
+        for i in 0..count {
+            let val = map
+                .entry(i)
+                .or_insert_with_key(|_| panic!("occupied entry ran closure"));
+            assert_eq!(*val, i * 2);
+        }
+        assert_eq!(map.len(), count as usize);
+    }
+
+    #[test]
+    fn map_entry_occupied_remove() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        match map.entry(1) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 10),
+            Entry::Vacant(_) => panic!("expected occupied entry"),
+        }
+
+        assert_eq!(map.len(), 1);
+        assert!(!map.contains_key(1));
+        assert!(map.assert_count());
+    }
+
+    #[test]
+    fn map_extend_with_hint() {
+        // An iterator that claims to be much shorter than it is.
+        struct Liar(std::ops::Range<u64>);
+
+        impl Iterator for Liar {
+            type Item = (u64, u64);
+
+            fn next(&mut self) -> Option<(u64, u64)> {
+                self.0.next().map(|i| (i, i))
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (0, Some(1))
+            }
+        }
+
+        let count = 10_000;
+        let mut map: IntMap<u64> = IntMap::new();
+        map.extend_with_hint(Liar(0..count), 10);
+
+        assert_eq!(map.len(), count as usize);
+        assert!(map.assert_count());
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        let mut map: IntMap<u64> = IntMap::new();
+        map.extend_with_hint(Liar(0..10), 10_000);
+        assert_eq!(map.len(), 10);
+    }
+
+    #[test]
+    fn map_clear_and_shrink() {
+        let count = 100_000;
+        let mut map: IntMap<u64> = IntMap::new();
+        let initial_capacity = map.capacity();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+        assert!(map.capacity() > initial_capacity);
+
+        map.clear_and_shrink();
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.capacity(), initial_capacity);
+        assert!(map.assert_count());
+
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        for i in 0..100 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_value_addresses_stable_across_reads() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        let addresses: Vec<(u64, *const u64)> =
+            map.iter_mut().map(|(k, v)| (*k, v as *const u64)).collect();
+
+        // Reads and in-place mutation must not move any value.
+        for i in 0..count {
+            assert!(map.contains_key(i));
+            *map.get_mut(i).unwrap() += 1;
+        }
+        assert_eq!(map.iter().count(), count as usize);
+
+        for (key, address) in addresses {
+            assert!(std::ptr::eq(map.get(key).unwrap(), address));
+        }
+    }
+
+    #[test]
+    fn map_get_mut_then_get() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        let address = {
+            let val = map.get_mut(1).unwrap();
+            *val += 1;
+            val as *const u64
+        };
+        assert_eq!(map.get(2), Some(&20));
+        assert!(std::ptr::eq(map.get(1).unwrap(), address));
+        assert_eq!(map.get(1), Some(&11));
+    }
+
+    #[test]
+    fn map_with_shape_of() {
+        let count = 5_000;
+        let mut map_1: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map_1.insert(i, i);
+        }
+
+        let mut map_2: IntMap<String> = IntMap::with_shape_of(&map_1);
+        assert_eq!(map_2.capacity(), map_1.capacity());
+        assert!(map_2.is_empty());
+
+        for i in (0..count).step_by(3) {
+            map_2.insert(i, format!("{}", i));
+        }
+        assert_eq!(map_2.capacity(), map_1.capacity());
+
+        for i in (0..count).step_by(3) {
+            let bucket_1 = map_1.get_mut_or_slot(i).1.unwrap().bucket();
+            let bucket_2 = map_2.get_mut_or_slot(i).1.unwrap().bucket();
+            assert_eq!(bucket_1, bucket_2);
+        }
+
+        // Seed, load rate and minimum capacity carry over.
+        let mut map_3: IntMap<u64> = IntMap::with_capacity_and_load(100, 25);
+        map_3.reseed(0xdead_beef);
+        let map_4: IntMap<u8> = IntMap::with_shape_of(&map_3);
+        assert_eq!(map_4.seed(), map_3.seed());
+        assert_eq!(map_4.capacity(), map_3.capacity());
+        assert_eq!(map_4.spare_capacity(), map_3.spare_capacity());
+
+        let map_5: IntMap<u64> = IntMap::with_min_capacity(256);
+        let mut map_6: IntMap<u64> = IntMap::with_shape_of(&map_5);
+        map_6.clear_and_shrink();
+        assert_eq!(map_6.capacity(), 256);
+    }
+
+    #[test]
+    fn map_recompute_count() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i, i);
+        }
+
+        // Leaking a drain loses the entries of the bucket it started on without
+        // updating the count.
+        std::mem::forget(map.drain());
+        assert!(!map.assert_count());
+
+        map.recompute_count();
+        assert!(map.assert_count());
+        assert_eq!(map.len(), map.iter().count());
+        assert!(map.len() < count as usize);
+    }
+
+    #[test]
+    fn map_try_for_each_stops_on_error() {
+        let count = 1_000;
+        let map = (0..count).map(|i| (i, i)).collect::<IntMap<u64>>();
+
+        let mut visited = 0;
+        let res = map.try_for_each(|_, _| {
+            visited += 1;
+            if visited == 10 {
+                Err("stop")
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(res, Err("stop"));
+        assert_eq!(visited, 10);
+
+        let mut visited = 0;
+        let res: Result<(), ()> = map.try_for_each(|_, _| {
+            visited += 1;
+            Ok(())
+        });
+        assert!(res.is_ok());
+        assert_eq!(visited, count);
+    }
+
+    #[test]
+    fn map_try_for_each_mut_stops_on_error() {
+        let count = 1_000;
+        let mut map = (0..count).map(|i| (i, i)).collect::<IntMap<u64>>();
+
+        let mut visited = 0;
+        let res = map.try_for_each_mut(|k, v| {
+            if visited == 10 {
+                return Err(k);
+            }
+            visited += 1;
+            *v += count;
+            Ok(())
+        });
+
+        let stopped_at = res.unwrap_err();
+        assert_eq!(map.get(stopped_at), Some(&stopped_at));
+        assert_eq!(map.values().filter(|v| **v >= count).count(), 10);
+    }
+
+    #[test]
+    fn map_reseed() {
+        let count = 20_000;
+        let data = get_random_range(count);
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for s in data.iter() {
+            map.insert(*s, *s);
+        }
+
+        let len = map.len();
+        let capacity = map.capacity();
+        map.reseed(0xdead_beef);
+        assert_eq!(map.seed(), 0xdead_beef);
+        assert_eq!(map.len(), len);
+        assert_eq!(map.capacity(), capacity);
+        assert!(map.assert_count());
+
+        for s in data.iter() {
+            assert_eq!(map.get(*s), Some(s));
+        }
+
+        map.reseed(0);
+        for s in data.iter() {
+            assert_eq!(map.get(*s), Some(s));
+        }
+    }
+
+    #[test]
+    fn map_reseed_clustered_keys() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            map.insert(i << 32, i);
+        }
+        assert_eq!(map.max_bucket_len(), count as usize);
+
+        map.reseed(0x1234_5678_9abc_def0);
+        assert!(map.max_bucket_len() < 16);
+
+        for i in 0..count {
+            assert_eq!(map.get(i << 32), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_reseed_high_bit_keys() {
+        // Keys only differing above the bits of any product that index a bucket.
+        let count = 2_000;
+        let mut map: IntMap<u64> = IntMap::new();
+        for i in 0..count {
+            map.insert(i << 44, i);
+        }
+        assert_eq!(map.max_bucket_len(), count as usize);
+
+        for &seed in [1, 12345, 0x5eed_5eed_5eed_5eed, 0xdead_beef_dead_beef].iter() {
+            map.reseed(seed);
+            assert!(map.max_bucket_len() < 16, "seed {:x}", seed);
+            for i in 0..count {
+                assert_eq!(map.get(i << 44), Some(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn map_expect_present() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(21, 42);
+
+        assert_eq!(*map.expect(21, "missing"), 42);
+        *map.expect_mut(21, "missing") += 1;
+        assert_eq!(*map.expect(21, "missing"), 43);
+    }
+
+    #[test]
+    #[should_panic(expected = "config value required: key 7 not found")]
+    fn map_expect_absent() {
+        let map: IntMap<u64> = IntMap::new();
+        map.expect(7, "config value required");
+    }
+
+    #[test]
+    #[should_panic(expected = "config value required: key 7 not found")]
+    fn map_expect_mut_absent() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.expect_mut(7, "config value required");
+    }
+
+    #[test]
+    fn map_bucket_length_distribution() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert_eq!(map.capacity(), 4);
+
+        // Sequential keys never collide, 0 and 1 << 40 always do.
+        map.insert(0, 0);
+        map.insert(1 << 40, 1);
+        map.insert(1, 2);
+
+        let distribution = map.bucket_length_distribution();
+        assert_eq!(distribution.len(), 3);
+        assert_eq!(distribution.get(0), Some(&2));
+        assert_eq!(distribution.get(1), Some(&1));
+        assert_eq!(distribution.get(2), Some(&1));
+
+        let total: u64 = distribution.values().sum();
+        assert_eq!(total, map.capacity() as u64);
+    }
+
+    #[test]
+    fn map_insert_mut() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            let val = map.insert_mut(i, i);
+            *val += 1;
+            let address = val as *const u64;
+            assert!(std::ptr::eq(map.get(i).unwrap(), address));
+        }
+        assert_eq!(map.len(), count as usize);
+        assert!(map.assert_count());
+
+        *map.insert_mut(5, 100) += 1;
+        assert_eq!(map.get(5), Some(&101));
+        assert_eq!(map.len(), count as usize);
+
+        for i in 0..count {
+            if i != 5 {
+                assert_eq!(map.get(i), Some(&(i + 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn map_get_or_insert_status() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            let (val, inserted) = map.get_or_insert_status(i, || i);
+            assert!(inserted);
+            assert_eq!(*val, i);
+        }
+
+        for i in 0..count {
+            let (val, inserted) =
+                map.get_or_insert_status(i, || panic!("closure ran for present key"));
+            assert!(!inserted);
+            *val += 1;
+        }
+
+        assert_eq!(map.len(), count as usize);
+        assert!(map.assert_count());
+        assert_eq!(map.get(10), Some(&11));
+    }
+
+    #[test]
+    fn map_capacity_always_power_of_two() {
+        use rand::{Rng, SeedableRng, StdRng};
+
+        let seed: &[_] = &[1, 2, 3, 4];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for _ in 0..5_000 {
+            match rng.gen_range(0, 10) {
+                0..=5 => {
+                    let key = rng.gen_range(0, 2_000);
+                    map.insert(key, key);
+                }
+                6 | 7 => {
+                    let key = rng.gen_range(0, 2_000);
+                    map.remove(key);
+                }
+                8 => map.reserve(rng.gen_range(0, 500)),
+                _ => {
+                    if rng.gen_range(0, 10) == 0 {
+                        map.clear_and_shrink();
+                    } else {
+                        map.resize_to_size(rng.gen_range(0, 14));
+                    }
+                }
+            }
+
+            let capacity = map.capacity();
+            assert!(capacity.is_power_of_two(), "capacity: {}", capacity);
+            assert!(map.assert_count());
+
+            // Every entry sits in the bucket masked by `capacity - 1`, so lookups find it.
+            for (bucket, key, value) in map.iter_with_bucket() {
+                assert!(bucket < capacity);
+                assert_eq!(map.get(key), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn map_probe_len() {
+        let mut map: IntMap<u64> = IntMap::new();
+
+        // Keys sharing their low bits collide under the default hash.
+        for i in 0..10 {
+            map.insert(i << 40, i);
+            assert_eq!(map.probe_len(0), (i + 1) as usize);
+        }
+
+        assert_eq!(map.probe_len(0), 10);
+        assert_eq!(map.probe_len(5 << 40), 10);
+        assert_eq!(map.probe_len(1 << 20), 10);
+        assert_eq!(map.probe_len(1), 0);
+
+        map.remove(3 << 40);
+        assert_eq!(map.probe_len(0), 9);
+    }
+
+    #[test]
+    fn map_iterators_visit_in_same_order() {
+        let data = get_random_range(5_000);
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for (i, s) in data.iter().enumerate() {
+            map.insert(*s, i as u64);
+        }
+
+        let pairs: Vec<(u64, u64)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        let zipped: Vec<(u64, u64)> = map
+            .keys()
+            .zip(map.values())
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        assert_eq!(pairs, zipped);
+
+        let values_mut: Vec<u64> = map.values_mut().map(|v| *v).collect();
+        let keys_mut: Vec<u64> = map.iter_mut().map(|(k, _)| *k).collect();
+        let keys: Vec<u64> = pairs.iter().map(|kv| kv.0).collect();
+        let values: Vec<u64> = pairs.iter().map(|kv| kv.1).collect();
+        assert_eq!(values_mut, values);
+        assert_eq!(keys_mut, keys);
+
+        let owned: Vec<(u64, u64)> = map.clone().into_iter().collect();
+        assert_eq!(owned, pairs);
+        let drained: Vec<(u64, u64)> = map.drain().collect();
+        assert_eq!(drained, pairs);
+    }
+
+    #[test]
+    fn map_insert_counting() {
+        let count = 1_000;
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..count {
+            assert_eq!(map.insert_counting(i, i), InsertOutcome::Inserted);
+        }
+        assert_eq!(map.len(), count as usize);
+
+        let mut replaced = 0;
+        for i in 0..(count * 2) {
+            match map.insert_counting(i, i + 1) {
+                InsertOutcome::Inserted => assert!(i >= count),
+                InsertOutcome::Replaced(old) => {
+                    assert_eq!(old, i);
+                    replaced += 1;
+                }
+            }
+        }
+
+        assert_eq!(replaced, count);
+        assert_eq!(map.len(), (count * 2) as usize);
+        assert!(map.assert_count());
+        assert_eq!(map.get(7), Some(&8));
+    }
+
+    #[test]
+    fn map_size_exponent() {
+        let mut map: IntMap<u64> = IntMap::new();
+
+        for i in 0..10_000 {
+            map.insert(i, i);
+            assert_eq!(2u64.pow(map.size_exponent()), map.capacity() as u64);
+        }
+
+        let size = map.size_exponent();
+        assert!(map.resize_to_size(size + 2));
+        assert_eq!(map.size_exponent(), size + 2);
+        assert!(map.resize_to_size(size));
+        assert_eq!(2u64.pow(map.size_exponent()), map.capacity() as u64);
+    }
+
+    #[test]
+    fn map_remove_keep_positions() {
+        let mut map: IntMap<u64> = IntMap::new();
+
+        // All in one bucket.
+        let keys: Vec<u64> = (0..10).map(|i| i << 40).collect();
+        for k in keys.iter() {
+            map.insert(*k, *k);
+        }
+
+        let slots: Vec<_> = keys
+            .iter()
+            .map(|k| map.get_mut_or_slot(*k).1.unwrap())
+            .collect();
+        let order: Vec<u64> = map.keys().copied().filter(|k| *k != keys[3]).collect();
+
+        assert_eq!(map.remove_keep_positions(keys[3]), Some(keys[3]));
+        assert_eq!(map.len(), 9);
+        assert!(map.assert_count());
+
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), order);
+        for (k, slot) in keys.iter().zip(slots.iter()) {
+            let expected = if *k == keys[3] { None } else { Some(k) };
+            assert_eq!(map.get_by_slot(*slot), expected);
+        }
+
+        // Holes in several buckets, then a plain `remove` that moves entries under them.
+        let mut map: IntMap<u64> = (0..200).map(|i| (i, i)).collect();
+        let slots: Vec<_> = (0..200)
+            .map(|k| map.get_mut_or_slot(k).1.unwrap())
+            .collect();
+        for k in (0..200).step_by(3) {
+            assert_eq!(map.remove_keep_positions(k), Some(k));
+        }
+        for k in (1..200).step_by(7) {
+            map.remove(k);
+        }
+
+        for (k, slot) in (0..200).zip(slots.iter()) {
+            assert_eq!(map.get_by_slot(*slot), map.get(k));
+        }
+        assert!(map.assert_count());
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn map_remove_keep_positions_no_scan() {
+        let mut map: IntMap<u64> = IntMap::new();
+
+        // All in one bucket.
+        let keys: Vec<u64> = (0..10).map(|i| i << 40).collect();
+        for k in keys.iter() {
+            map.insert(*k, *k);
+        }
+        let slots: Vec<_> = keys
+            .iter()
+            .map(|k| map.get_mut_or_slot(*k).1.unwrap())
+            .collect();
+
+        map.remove_keep_positions(keys[3]);
+        map.remove_keep_positions(keys[7]);
+        map.remove_keep_positions(keys[0]);
+
+        // Handles taken before and after the holes appeared resolve at their position.
+        map.insert(10 << 40, 10 << 40);
+        let late = map.get_mut_or_slot(keys[8]).1.unwrap();
+        let new = map.get_mut_or_slot(10 << 40).1.unwrap();
+        assert_eq!(late, slots[8]);
+
+        let scans = map.stats().slot_scans;
+        for (i, k) in keys.iter().enumerate() {
+            if ![0, 3, 7].contains(&i) {
+                assert_eq!(map.get_by_slot(slots[i]), Some(k));
+                *map.get_mut_by_slot(slots[i]).unwrap() += 1;
+            }
+        }
+        assert_eq!(map.get_by_slot(late), Some(&(keys[8] + 1)));
+        assert_eq!(map.get_by_slot(new), Some(&(10 << 40)));
+        assert_eq!(map.stats().slot_scans, scans);
+
+        // Handles to removed entries miss, after scanning in case the key came back.
+        assert_eq!(map.get_by_slot(slots[3]), None);
+        assert_eq!(map.stats().slot_scans, scans + 1);
+        map.insert(keys[3], 3);
+        assert_eq!(map.get_by_slot(slots[3]), Some(&3));
+
+        // A plain remove moves the last entry, only its handles scan.
+        let scans = map.stats().slot_scans;
+        map.remove(keys[1]);
+        assert_eq!(map.get_by_slot(slots[2]), Some(&(keys[2] + 1)));
+        assert_eq!(map.stats().slot_scans, scans);
+    }
+
+    #[test]
+    fn map_slots_survive_remove() {
+        let mut map: IntMap<u64> = IntMap::new();
+        let keys: Vec<u64> = (0..10).map(|i| i << 40).collect();
+        for k in keys.iter() {
+            map.insert(*k, *k);
+        }
+
+        let slot = map.get_mut_or_slot(keys[9]).1.unwrap();
+        // Moves the last entry of the bucket into the gap.
+        map.remove(keys[0]);
+        assert_eq!(map.get_by_slot(slot), Some(&keys[9]));
+        *map.get_mut_by_slot(slot).unwrap() += 1;
+        assert_eq!(map.get(keys[9]), Some(&(keys[9] + 1)));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn map_stats() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert_eq!(map.stats(), intmap::MapStats::default());
+
+        // Sequential keys never collide while they fit, so every hit costs one comparison.
+        map.insert(0, 0);
+        map.insert(1, 1);
+        map.insert(2, 2);
+        assert_eq!(map.get(0), Some(&0));
+        assert!(map.contains_key(2));
+        assert_eq!(map.get(3), None);
+
+        let stats = map.stats();
+        assert_eq!(stats.gets, 3);
+        assert_eq!(stats.comparisons, 2);
+        assert_eq!(stats.resizes, 0);
+
+        // Both keys share bucket 0, a miss compares against both.
+        map.insert(1 << 40, 3);
+        assert_eq!(map.get(2 << 40), None);
+        let stats = map.stats();
+        assert_eq!(stats.gets, 4);
+        assert_eq!(stats.comparisons, 4);
+        assert_eq!(stats.resizes, 1);
+        assert_eq!(stats.comparisons_per_get(), 1.0);
+    }
+
+    #[test]
+    fn map_top_n() {
+        let words = [1, 2, 3, 1, 4, 1, 2, 5, 2, 1, 3, 6, 7, 3, 1, 2];
+        let mut counts: IntMap<u64> = IntMap::new();
+        for w in words.iter() {
+            *counts.entry(*w).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.top_n(3), [(1, &5), (2, &4), (3, &3)]);
+        assert_eq!(counts.top_n(0), []);
+        assert_eq!(counts.top_n(100).len(), counts.len());
+
+        let sorted: Vec<(u64, &u64)> = counts.iter_by_value_desc().collect();
+        assert_eq!(sorted.len(), counts.len());
+        assert_eq!(&sorted[..3], &counts.top_n(3)[..]);
+        assert!(sorted.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn map_top_n_matches_full_sort() {
+        let data = get_random_range(5_000);
+        let map: IntMap<u64> = data.iter().map(|k| (*k, *k % 1_000)).collect();
+
+        let sorted: Vec<(u64, &u64)> = map.iter_by_value_desc().collect();
+        assert_eq!(map.top_n(50), &sorted[..50]);
+    }
+
+    #[test]
+    fn map_add_assign_map() {
+        let mut a: IntMap<i64> = IntMap::new();
+        let mut b: IntMap<i64> = IntMap::new();
+        for i in 0..100 {
+            a.insert(i * 2, 1);
+            b.insert(i * 3, -5);
+        }
+
+        a.add_assign_map(&b);
+
+        assert_eq!(a.len(), 100 + 100 - 34);
+        assert!(a.assert_count());
+        for i in 0..300 {
+            let expected = match (i % 2 == 0 && i < 200, i % 3 == 0) {
+                (true, true) => Some(-4),
+                (true, false) => Some(1),
+                (false, true) => Some(-5),
+                (false, false) => None,
+            };
+            assert_eq!(a.get(i).copied(), expected, "key {}", i);
+        }
+        assert_eq!(b.len(), 100);
+    }
+
+    #[test]
+    fn map_boxed_values() {
+        let mut map: IntMap<Box<[u64; 64]>> = IntMap::new();
+        for i in 0..100 {
+            assert!(map.insert_boxed(i, [i; 64]));
+        }
+        assert!(!map.insert_boxed(1, [0; 64]));
+
+        for i in 0..100 {
+            let value: &[u64; 64] = map.get_boxed(i).unwrap();
+            assert!(value.iter().all(|v| *v == i));
+        }
+        assert_eq!(map.get_boxed(100), None);
+
+        map.get_boxed_mut(7).unwrap()[0] = 42;
+        assert_eq!(map.get_boxed(7).unwrap()[0], 42);
+        assert_eq!(
+            map.iter_boxed().map(|(_, v)| v[1]).sum::<u64>(),
+            (0..100).sum()
+        );
+
+        let removed: [u64; 64] = map.remove_boxed(7).unwrap();
+        assert_eq!(removed[..2], [42, 7]);
+        assert_eq!(map.remove_boxed(7), None);
+        assert_eq!(map.len(), 99);
+
+        let mut strs: IntMap<Box<str>> = IntMap::new();
+        strs.insert(1, "one".into());
+        assert_eq!(strs.get_boxed(1), Some("one"));
+    }
+
+    #[test]
+    fn map_tx_rollback() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data[..500].iter() {
+            map.insert(*k, *k);
+        }
+        let before = map.clone();
+
+        let mut tx = map.begin_tx();
+        for k in data.iter() {
+            tx.insert(*k, 0);
+        }
+        for k in data[250..750].iter() {
+            tx.replace(*k, 1);
+        }
+        assert!(tx.replace(data[0], 2));
+        assert!(tx.replace(data[0], 3));
+        assert_eq!(tx.len(), 1000);
+        tx.rollback();
+
+        assert_eq!(map, before);
+        assert!(map.assert_count());
+    }
+
+    #[test]
+    fn map_tx_commit_and_drop() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+
+        let mut tx = map.begin_tx();
+        assert!(!tx.insert(1, 11));
+        assert!(tx.insert(2, 20));
+        tx.commit();
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(2), Some(&20));
+
+        {
+            let mut tx = map.begin_tx();
+            assert!(!tx.replace(3, 30));
+            assert!(tx.replace(1, 12));
+        }
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(3), None);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn map_rekey() {
+        let mut map: IntMap<Vec<u64>> = IntMap::new();
+        for i in 0..100 {
+            map.insert(i, vec![i]);
+        }
+        let capacity = map.capacity();
+
+        for i in 0..100 {
+            assert!(map.rekey(i, i + 1000));
+        }
+
+        assert_eq!(map.len(), 100);
+        assert!(map.assert_count());
+        assert_eq!(map.capacity(), capacity);
+        for i in 0..100 {
+            assert!(!map.contains_key(i));
+            assert_eq!(map.get(i + 1000), Some(&vec![i]));
+        }
+    }
+
+    #[test]
+    fn map_rekey_missing_old() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+
+        assert!(!map.rekey(2, 3));
+        assert!(!map.rekey(2, 2));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(1), Some(&10));
+        assert!(!map.contains_key(3));
+    }
+
+    #[test]
+    fn map_rekey_occupied_new() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        assert!(!map.rekey(1, 2));
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(2), Some(&20));
+
+        assert!(map.rekey(1, 1));
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn map_contains_mask() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter().step_by(2) {
+            map.insert(*k, 0);
+        }
+
+        let mut keys: Vec<u64> = data.clone();
+        keys.extend_from_slice(&data[..13]);
+
+        let mask = map.contains_mask(&keys);
+        assert_eq!(mask.len(), keys.len());
+        for (i, (k, present)) in keys.iter().zip(mask.iter()).enumerate() {
+            assert_eq!(*present, map.contains_key(*k));
+            assert_eq!(*present, (i % 1000) % 2 == 0);
+        }
+
+        assert!(map.contains_mask(&[]).is_empty());
+    }
+
+    #[test]
+    fn map_freeze_capacity() {
+        let mut map: IntMap<u64> = IntMap::with_capacity(128);
+        let capacity = map.capacity();
+        map.freeze_capacity();
+
+        for i in 0..1_000 {
+            assert!(map.insert(i, i));
+            assert_eq!(map.capacity(), capacity);
+        }
+        map.entry(1_000).or_insert(0);
+        *map.insert_mut(1_001, 0) += 1;
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.len(), 1_002);
+        for i in 0..1_000 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        map.unfreeze_capacity();
+        assert!(map.capacity() > capacity);
+        assert!(map.load_rate() <= 70.0);
+        assert_eq!(map.len(), 1_002);
+        for i in 0..1_000 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_content_hash() {
+        let data = get_random_range(1000);
+
+        let mut a: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            a.insert(*k, *k % 7);
+        }
+
+        let mut b: IntMap<u64> = IntMap::with_capacity(4096);
+        b.reseed(42);
+        for k in data.iter().rev() {
+            b.insert(*k, *k % 7);
+        }
+
+        assert_eq!(a, b);
+        assert_eq!(a.content_hash(1), b.content_hash(1));
+        assert_ne!(a.content_hash(1), a.content_hash(2));
+
+        *b.get_mut(data[500]).unwrap() += 1;
+        assert_ne!(a.content_hash(1), b.content_hash(1));
+
+        *b.get_mut(data[500]).unwrap() -= 1;
+        b.remove(data[0]);
+        assert_ne!(a.content_hash(1), b.content_hash(1));
+
+        let empty: IntMap<u64> = IntMap::new();
+        assert_eq!(empty.content_hash(1), 0);
+    }
+
+    #[test]
+    fn map_content_hash_is_stable() {
+        // Pinned, so digests stay comparable across builds, platforms and versions.
+        let ints: IntMap<u64> = (0..100).map(|i| (i, i * 3)).collect();
+        assert_eq!(ints.content_hash(0), 0xe0a2e08f924b14e0);
+
+        let strings: IntMap<String> = (0..10).map(|i| (i, format!("v{}", i))).collect();
+        assert_eq!(strings.content_hash(7), 0x9bf89e9ca05fe578);
+
+        let tuples: IntMap<(usize, i32)> =
+            (0..10).map(|i| (i, (i as usize, -(i as i32)))).collect();
+        assert_eq!(tuples.content_hash(1), 0xdfb8a293aa13225e);
+    }
+
+    #[test]
+    fn map_interned_strings() {
+        let words = ["foo", "bar", "baz", "foo", "qux", "bar"];
+
+        let mut ids: std::collections::HashMap<&str, u64> = std::collections::HashMap::new();
+        let mut names: IntMap<Box<str>> = IntMap::new();
+        for word in words.iter() {
+            let next = ids.len() as u64;
+            let id = *ids.entry(word).or_insert(next);
+            names.insert_str(id, word);
+        }
+
+        assert_eq!(names.len(), 4);
+        for (word, id) in ids.iter() {
+            assert_eq!(names.get_str(*id), Some(*word));
+        }
+        assert_eq!(names.get_str(4), None);
+
+        assert!(!names.insert_str(0, "other"));
+        assert_eq!(names.get_str(0), Some("foo"));
+    }
+
+    #[test]
+    fn map_values_in_order() {
+        let mut map: IntMap<u64> = IntMap::new();
+        for i in (0..100).filter(|i| i % 3 != 0) {
+            map.insert(i, i * 10);
+        }
+
+        let keys: Vec<u64> = (0..100).rev().collect();
+        let values: Vec<Option<&u64>> = map.values_in_order(&keys).collect();
+
+        assert_eq!(values.len(), keys.len());
+        for (k, v) in keys.iter().zip(values.iter()) {
+            if k % 3 == 0 {
+                assert_eq!(*v, None, "key {}", k);
+            } else {
+                assert_eq!(*v, Some(&(k * 10)));
+            }
+        }
+        assert_eq!(map.values_in_order(&[]).count(), 0);
+    }
+
+    #[test]
+    fn map_auto_shrink() {
+        let data = get_random_range(10_000);
+        let mut map: IntMap<u64> = IntMap::new();
+        map.set_auto_shrink(true);
+        for k in data.iter() {
+            map.insert(*k, *k);
+        }
+        let full = map.capacity();
+
+        for k in data[100..].iter() {
+            assert_eq!(map.remove(*k), Some(*k));
+            assert!(map.load_rate() >= 15.0 || map.capacity() == 4);
+        }
+
+        assert!(map.capacity() < full);
+        assert!(map.load_rate() <= 30.0);
+        assert_eq!(map.len(), 100);
+        for k in data[..100].iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+
+        map.retain(|_, _| false);
+        assert_eq!(map.capacity(), 4);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn map_auto_shrink_other_removals() {
+        let full: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+
+        let mut map = full.clone();
+        map.set_auto_shrink(true);
+        for i in 0..1_000 {
+            if let Entry::Occupied(entry) = map.entry(i) {
+                assert_eq!(entry.remove(), i);
+            }
+        }
+        assert_eq!(map.capacity(), 4);
+
+        let mut map = full.clone();
+        map.set_auto_shrink(true);
+        let mut cursor = map.cursor_mut();
+        while cursor.next().is_some() {
+            cursor.remove_current();
+        }
+        assert_eq!(map.capacity(), 4);
+
+        let mut map: IntMap<u64> = IntMap::new();
+        map.set_auto_shrink(true);
+        let mut tx = map.begin_tx();
+        for i in 0..1_000 {
+            tx.insert(i, i);
+        }
+        tx.rollback();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 4);
+    }
+
+    #[test]
+    fn map_auto_shrink_hysteresis() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.set_auto_shrink(true);
+        for i in 0..1_000 {
+            map.insert(i, i);
+        }
+
+        // Hover around the shrink threshold, then around the grow threshold.
+        map.retain(|k, _| k < 200);
+        let capacity = map.capacity();
+        for i in 0..1_000 {
+            map.remove(i % 50);
+            map.insert(i % 50, 0);
+            assert_eq!(map.capacity(), capacity);
+        }
+
+        let mut resizes = 0;
+        let mut last = map.capacity();
+        for i in 0..1_000 {
+            map.insert(10_000 + i % 20, 0);
+            map.remove(10_000 + (i + 10) % 20);
+            if map.capacity() != last {
+                resizes += 1;
+                last = map.capacity();
+            }
+        }
+        assert!(resizes <= 1);
+    }
+
+    #[test]
+    fn map_auto_shrink_disabled() {
+        let mut map: IntMap<u64> = (0..1_000).map(|i| (i, i)).collect();
+        let capacity = map.capacity();
+
+        map.retain(|k, _| k < 10);
+        for i in 0..10 {
+            map.remove(i);
+        }
+        assert_eq!(map.capacity(), capacity);
+    }
+
+    #[test]
+    fn map_reserve_for_keys() {
+        let data = get_random_range(10_000);
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(data[0], 42);
+
+        map.reserve_for_keys(data.iter().chain(data[..100].iter()).copied());
+        let capacity = map.capacity();
+
+        assert_eq!(map.len(), data.len());
+        assert!(map.assert_count());
+        assert!(map.load_rate() <= 70.0);
+        assert_eq!(map.get(data[0]), Some(&42));
+
+        for k in data[1..].iter() {
+            assert_eq!(map.get(*k), Some(&0));
+            *map.insert_mut(*k, 0) = *k;
+            assert_eq!(map.capacity(), capacity);
+        }
+        for k in data[1..].iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+
+        // Inserting keys the map was sized for never resizes.
+        let mut fresh: IntMap<u64> = IntMap::new();
+        fresh.reserve_for_keys(data.iter().copied());
+        let capacity = fresh.capacity();
+        fresh.clear();
+        for k in data.iter() {
+            fresh.insert(*k, *k);
+        }
+        assert_eq!(fresh.capacity(), capacity);
+    }
+
+    #[test]
+    fn map_worst_bucket_keys() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert!(map.worst_bucket_keys().is_empty());
+
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        // Keys differing only above bit 40 share a bucket under the default hash.
+        let colliding: Vec<u64> = (1..=10).map(|i| (i << 40) | 7).collect();
+        for k in colliding.iter() {
+            map.insert(*k, 0);
+        }
+
+        let mut keys = map.worst_bucket_keys();
+        assert_eq!(keys.len(), map.max_bucket_len());
+        keys.sort();
+
+        let mut expected = colliding.clone();
+        expected.push(7);
+        expected.sort();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn map_insert_or_update_running_max() {
+        let data = get_random_range(5_000);
+        let mut max: IntMap<u64> = IntMap::new();
+        let mut expected = std::collections::HashMap::new();
+
+        for (i, v) in data.iter().enumerate() {
+            let key = (i % 97) as u64;
+            max.insert_or_update(key, *v, |m| {
+                if *v > *m {
+                    *m = *v;
+                }
+            });
+            let e = expected.entry(key).or_insert(*v);
+            *e = (*e).max(*v);
+        }
+
+        assert_eq!(max.len(), 97);
+        for (k, v) in expected.iter() {
+            assert_eq!(max.get(*k), Some(v));
+        }
+
+        let mut calls = 0;
+        max.insert_or_update(1_000, 0, |_| calls += 1);
+        assert_eq!(calls, 0);
+        assert_eq!(max.get(1_000), Some(&0));
+    }
+
+    #[test]
+    fn map_drain_to_vec() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            map.insert(*k, *k + 1);
+        }
+        let len = map.len();
+        let capacity = map.capacity();
+
+        let mut entries = map.drain_to_vec();
+        assert_eq!(entries.len(), len);
+        assert_eq!(entries.capacity(), len);
+        assert!(entries.iter().all(|(k, v)| *v == *k + 1));
+        entries.sort();
+        let mut keys = data.clone();
+        keys.sort();
+        assert!(entries.iter().map(|kv| kv.0).eq(keys.into_iter()));
+
+        assert!(map.is_empty());
+        assert!(map.assert_count());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.capacity(), capacity);
+
+        assert!(map.drain_to_vec().is_empty());
+    }
+
+    #[test]
+    fn map_into_sorted_vec_is_sorted() {
+        let data = get_random_range(1000);
+        let map: IntMap<u64> = data.iter().map(|k| (*k, *k / 2)).collect();
+
+        let entries = map.into_sorted_vec();
+        assert_eq!(entries.len(), data.len());
+        assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(entries.iter().all(|(k, v)| *v == *k / 2));
+    }
+
+    #[test]
+    fn map_sorted_vec_round_trip() {
+        let data = get_random_range(1000);
+        let map: IntMap<u64> = data.iter().map(|k| (*k, *k / 2)).collect();
+
+        let restored = IntMap::from_sorted_vec(map.clone().into_sorted_vec());
+        assert_eq!(restored, map);
+        assert_eq!(restored.len(), map.len());
+        assert!(restored.assert_count());
+        assert!(restored.load_rate() <= 70.0);
+
+        assert_eq!(restored.into_sorted_vec(), map.into_sorted_vec());
+
+        let empty: IntMap<u64> = IntMap::from_sorted_vec(Vec::new());
+        assert!(empty.into_sorted_vec().is_empty());
+    }
+
+    #[test]
+    fn map_generation() {
+        let mut map: IntMap<u64> = IntMap::new();
+        let start = map.generation();
+
+        map.insert(1, 1);
+        map.insert(1, 2);
+        *map.get_mut(1).unwrap() = 3;
+        map.insert_mut(1, 4);
+        assert_eq!(map.generation(), start + 1);
+
+        map.entry(2).or_insert(0);
+        if let Entry::Occupied(e) = map.entry(2) {
+            e.remove();
+        }
+        assert_eq!(map.generation(), start + 3);
+
+        for i in 0..10 {
+            map.insert(i, i);
+        }
+        let before = map.generation();
+        map.retain(|k, _| k % 2 == 0);
+        assert_eq!(map.generation(), before + 5);
+        map.clear();
+        assert_eq!(map.generation(), before + 10);
+    }
+
+    #[cfg(feature = "change-tracking")]
+    #[test]
+    fn map_iter_since() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data[..500].iter() {
+            map.insert(*k, 0);
+        }
+
+        let mark = map.generation();
+        assert_eq!(map.iter_since(mark).count(), 0);
+
+        for k in data[500..].iter() {
+            map.insert(*k, 1);
+        }
+        // Overwriting old entries does not make them new, reinserting does.
+        for k in data[..100].iter() {
+            *map.get_mut(*k).unwrap() = 2;
+        }
+        map.remove(data[100]);
+        map.insert(data[100], 3);
+
+        let mut since: Vec<u64> = map.iter_since(mark).map(|(k, _)| k).collect();
+        since.sort();
+        let mut expected: Vec<u64> = data[500..].to_vec();
+        expected.push(data[100]);
+        expected.sort();
+        assert_eq!(since, expected);
+
+        assert_eq!(map.iter_since(0).count(), map.len());
+        map.clear();
+        assert_eq!(map.iter_since(0).count(), 0);
+    }
+
+    #[test]
+    fn map_into_shards() {
+        let data = get_random_range(10_000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            map.insert(*k, *k + 1);
+        }
+        let original = map.clone();
+
+        let shards = map.into_shards(4);
+        assert_eq!(shards.len(), 4);
+        assert_eq!(
+            shards.iter().map(|s| s.len()).sum::<usize>(),
+            original.len()
+        );
+
+        for (i, shard) in shards.iter().enumerate() {
+            assert!(shard.len() > 2_000, "shard {} has {}", i, shard.len());
+            for other in shards[i + 1..].iter() {
+                assert!(shard.is_disjoint(other));
+            }
+            for (k, v) in shard.iter() {
+                assert_eq!(original.get(*k), Some(v));
+            }
+        }
+
+        // Strided keys are still spread evenly.
+        let strided: IntMap<u64> = (0..1_000).map(|i| (i * 4, i)).collect();
+        assert!(strided.into_shards(4).iter().all(|s| s.len() > 200));
+    }
+
+    #[test]
+    fn map_for_each_entry_upsert() {
+        let mut map: IntMap<u64> = IntMap::new();
+        for i in 0..50 {
+            map.insert(i, i);
+        }
+
+        let batch: Vec<u64> = (25..100).chain(90..100).collect();
+        let mut vacant = 0;
+        map.for_each_entry(&batch, |entry| match entry {
+            Entry::Occupied(mut e) => *e.get_mut() += 1000,
+            Entry::Vacant(e) => {
+                vacant += 1;
+                let key = e.key();
+                e.insert(key * 2);
+            }
+        });
+
+        assert_eq!(vacant, 50);
+        assert_eq!(map.len(), 100);
+        assert!(map.assert_count());
+        for i in 0..25 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+        for i in 25..50 {
+            assert_eq!(map.get(i), Some(&(i + 1000)));
+        }
+        for i in 50..90 {
+            assert_eq!(map.get(i), Some(&(i * 2)));
+        }
+        // Repeated keys see the entry inserted earlier in the batch.
+        for i in 90..100 {
+            assert_eq!(map.get(i), Some(&(i * 2 + 1000)));
+        }
+    }
+
+    #[test]
+    fn map_optimize_for_high_bit_keys() {
+        // Keys only differing in their highest bits share a bucket under the default hash.
+        let keys: Vec<u64> = (0..2_000).map(|i| i << 44).collect();
+        let mut map: IntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+        assert_eq!(map.max_bucket_len(), keys.len());
+
+        map.optimize_for(&keys);
+        assert_ne!(map.seed(), 0);
+        assert!(map.max_bucket_len() < 16);
+        for k in keys.iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+    }
+
+    #[test]
+    fn map_optimize_for_clustered_sample() {
+        // Keys on a coarse grid, which collide heavily under the default hash.
+        let keys: Vec<u64> = (0..2_000)
+            .map(|i| (i % 40) << 32 | (i / 40) << 48)
+            .collect();
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in keys.iter() {
+            map.insert(*k, *k);
+        }
+        let before = map.max_bucket_len();
+        let capacity = map.capacity();
+
+        map.optimize_for(&keys[..500]);
+
+        assert!(map.max_bucket_len() <= before);
+        assert!(
+            map.max_bucket_len() < before / 10,
+            "{} vs {}",
+            map.max_bucket_len(),
+            before
+        );
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.len(), keys.len());
+        for k in keys.iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+
+        // Optimizing again never makes things worse, an empty sample changes nothing.
+        let after = map.max_bucket_len();
+        map.optimize_for(&keys);
+        assert!(map.max_bucket_len() <= after);
+        let seed = map.seed();
+        map.optimize_for(&[]);
+        assert_eq!(map.seed(), seed);
+    }
+
+    #[test]
+    fn map_with_capacity_fits_without_resize() {
+        for n in [0, 1, 5, 7, 100, 143, 1_000, 10_000].iter() {
+            let mut map: IntMap<u64> = IntMap::with_capacity(*n);
+            let capacity = map.capacity();
+            assert!(capacity.is_power_of_two());
+
+            for i in 0..*n as u64 {
+                assert!(map.insert(i, i));
+                assert_eq!(map.capacity(), capacity, "n {} after {} inserts", n, i + 1);
+            }
+        }
+
+        let mut map: IntMap<u64> = IntMap::with_capacity(100);
+        let capacity = map.capacity();
+        for i in 0..100 {
+            map.insert(i * 7919, i);
+        }
+        assert_eq!(map.capacity(), capacity);
+    }
+
+    #[test]
+    fn map_get_mut_or_else_insert_with() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        let mut calls = 0;
+
+        for _ in 0..3 {
+            for k in data.iter() {
+                *map.get_mut_or_else_insert_with(*k, || {
+                    calls += 1;
+                    0
+                }) += 1;
+            }
+        }
+
+        assert_eq!(calls, data.len());
+        assert_eq!(map.len(), data.len());
+        assert!(map.values().all(|v| *v == 3));
+    }
+
+    #[test]
+    fn map_signed_keys_i8_exhaustive() {
+        // Signed keys are stored through the two's complement cast, which is injective:
+        // every i8 gets its own key and -1 is not confused with any other value.
+        let mut map: IntMap<i8> = IntMap::new();
+        for k in i8::MIN..=i8::MAX {
+            assert!(map.insert(k as i64 as u64, k));
+        }
+
+        assert_eq!(map.len(), 256);
+        for k in i8::MIN..=i8::MAX {
+            assert_eq!(map.get(k as i64 as u64), Some(&k));
+        }
+        assert_eq!(map.get(-1i64 as u64), Some(&-1));
+        assert_eq!(map.get(255), None);
+        assert_eq!(map.get(128), None);
+
+        // Flipping the sign bit on top of the cast also preserves order.
+        let encode = |k: i8| (k as i64 as u64) ^ (1 << 63);
+        for a in i8::MIN..i8::MAX {
+            assert!(encode(a) < encode(a + 1));
+        }
+    }
+
+    #[test]
+    fn map_pop_min_max_drain_sorted() {
+        let data = get_random_range(300);
+        let mut sorted = data.clone();
+        sorted.sort();
+
+        let mut map: IntMap<u64> = data.iter().map(|k| (*k, *k / 3)).collect();
+        let mut ascending = Vec::new();
+        while let Some((k, v)) = map.pop_min() {
+            assert_eq!(v, k / 3);
+            ascending.push(k);
+            assert_eq!(map.len(), data.len() - ascending.len());
+        }
+        assert_eq!(ascending, sorted);
+        assert!(map.is_empty());
+        assert_eq!(map.pop_min(), None);
+
+        let mut map: IntMap<u64> = data.iter().map(|k| (*k, *k / 3)).collect();
+        let mut descending = Vec::new();
+        while let Some((k, _)) = map.pop_max() {
+            descending.push(k);
+        }
+        sorted.reverse();
+        assert_eq!(descending, sorted);
+        assert!(map.assert_count());
+        assert_eq!(map.pop_max(), None);
+    }
+
+    #[test]
+    fn map_from_iter_checked() {
+        let data = get_random_range(10_000);
+        let map = IntMap::from_iter_checked(data.iter().map(|k| (*k, *k)), 16).unwrap();
+        assert_eq!(map.len(), data.len());
+        assert_eq!(map.seed(), 0);
+
+        // Adversarial keys are recovered from by reseeding if the limit allows it.
+        let adversarial: Vec<(u64, u64)> = (0..5_000).map(|i| (i << 32, i)).collect();
+        let map = IntMap::from_iter_checked(adversarial.clone(), 16).unwrap();
+        assert_ne!(map.seed(), 0);
+        assert!(map.max_bucket_len() <= 16);
+        for (k, v) in adversarial.iter() {
+            assert_eq!(map.get(*k), Some(v));
+        }
+
+        // So are keys only differing in their highest bits.
+        let high_bits: Vec<(u64, u64)> = (0..5_000).map(|i| (i << 44, i)).collect();
+        let map = IntMap::from_iter_checked(high_bits.clone(), 16).unwrap();
+        assert_ne!(map.seed(), 0);
+        assert!(map.max_bucket_len() <= 16);
+        for (k, v) in high_bits.iter() {
+            assert_eq!(map.get(*k), Some(v));
+        }
+
+        // No seed spreads 10k keys over 16k buckets without any collision.
+        let err = IntMap::from_iter_checked(data.iter().map(|k| (*k, *k)), 1).unwrap_err();
+        assert_eq!(err.max_chain, 1);
+        assert!(err.max_bucket_len > 1);
+        let _: TooClustered = err;
+        assert!(err.to_string().contains("at most 1 allowed"));
+    }
+
+    #[test]
+    fn map_bucket_load_iter() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            map.insert(*k, *k);
+        }
+
+        let loads: Vec<(usize, usize)> = map.bucket_load_iter().collect();
+        assert_eq!(loads.len(), map.capacity());
+        assert!(loads.iter().enumerate().all(|(i, (ix, _))| i == *ix));
+        assert_eq!(loads.iter().map(|(_, len)| len).sum::<usize>(), map.len());
+        assert!(loads.iter().any(|(_, len)| *len == 0));
+        assert_eq!(
+            loads.iter().map(|(_, len)| *len).max(),
+            Some(map.max_bucket_len())
+        );
+
+        for k in data.iter() {
+            let (_, len) = loads[map.iter_with_bucket().find(|e| e.1 == *k).unwrap().0];
+            assert_eq!(len, map.probe_len(*k));
+        }
+    }
+
+    #[test]
+    fn map_remove_if_present_matching() {
+        let mut map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+
+        for i in (0..100).step_by(2) {
+            assert_eq!(map.remove_if(i, |v| *v % 2 == 0), Some(i));
+        }
+        assert_eq!(map.len(), 50);
+        assert!(map.assert_count());
+        assert!(map.keys().all(|k| k % 2 == 1));
+    }
+
+    #[test]
+    fn map_remove_if_present_not_matching() {
+        let mut map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+
+        for i in 0..100 {
+            assert_eq!(map.remove_if(i, |v| *v >= 100), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert!(map.assert_count());
+        for i in 0..100 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn map_remove_if_absent() {
+        let mut map: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+
+        let mut calls = 0;
+        for i in 100..200 {
+            assert_eq!(
+                map.remove_if(i, |_| {
+                    calls += 1;
+                    true
+                }),
+                None
+            );
+        }
+        assert_eq!(calls, 0);
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn map_raw_buckets_round_trip() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            map.insert(*k, *k + 1);
+        }
+        map.reseed(0xdead_beef);
+
+        let buckets: Vec<Vec<(u64, u64)>> = map.raw_buckets().to_vec();
+        assert_eq!(buckets.len(), map.capacity());
+        assert_eq!(buckets.iter().map(|b| b.len()).sum::<usize>(), map.len());
+
+        let mut restored = IntMap::from_raw_buckets(buckets, map.seed());
+        assert_eq!(restored.len(), map.len());
+        assert_eq!(restored, map);
+        assert_eq!(map, restored);
+        assert_eq!(restored.capacity(), map.capacity());
+        assert!(restored.assert_count());
+        assert_eq!(restored.raw_buckets(), map.raw_buckets());
+
+        for k in data.iter() {
+            assert_eq!(restored.get(*k), Some(&(*k + 1)));
+        }
+        restored.insert(1, 1);
+        assert_eq!(restored.get(1), Some(&1));
+    }
+
+    #[test]
+    fn map_keys_collide() {
+        let mut map: IntMap<u64> = IntMap::with_capacity(1000);
+        let bits = map.size_exponent();
+
+        // Under the default hash, keys equal in their low `bits` bits share a bucket.
+        let a = 0x1234;
+        let b = a + (1 << bits);
+        assert!(map.keys_collide(a, b));
+        assert!(map.keys_collide(a, a));
+        assert!(!map.keys_collide(a, a + 1));
+
+        map.insert(a, 1);
+        map.insert(b, 2);
+        assert_eq!(map.max_bucket_len(), 2);
+        let mut worst = map.worst_bucket_keys();
+        worst.sort();
+        assert_eq!(worst, [a, b]);
+
+        // Reseeding changes which keys collide.
+        map.reseed(0x5eed);
+        let colliding = (0..1000).filter(|i| map.keys_collide(0, *i)).count();
+        assert!(colliding < 10);
+        for i in 0..1000 {
+            assert_eq!(map.keys_collide(0, i), map.keys_collide(i, 0));
+        }
+    }
+
+    #[test]
+    fn map_on_resize() {
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&events);
+
+        let mut map: IntMap<u64> = IntMap::new();
+        map.on_resize(move |old, new| log.lock().unwrap().push((old, new)));
+
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        let grown = events.lock().unwrap().clone();
+        assert!(!grown.is_empty());
+        assert_eq!(grown[0].0, 4);
+        assert!(grown.windows(2).all(|w| w[0].1 == w[1].0));
+        assert!(grown.iter().all(|(old, new)| new > old));
+        assert_eq!(grown.last().unwrap().1, map.capacity());
+
+        // Rebuilding at the same capacity is not a resize, and clones have no callback.
+        map.reseed(7);
+        let mut clone = map.clone();
+        clone.reserve(10_000);
+        assert_eq!(events.lock().unwrap().len(), grown.len());
+
+        map.clear_and_shrink();
+        assert_eq!(
+            events.lock().unwrap().last(),
+            Some(&(grown.last().unwrap().1, 4))
+        );
+    }
+
+    #[test]
+    fn map_stays_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut map: IntMap<u64> = IntMap::new();
+        map.on_resize(|_, _| {});
+        assert_send_sync(&map);
+    }
+
+    #[test]
+    fn map_map_values_to_string() {
+        let data = get_random_range(1000);
+        let mut map: IntMap<u64> = IntMap::new();
+        for k in data.iter() {
+            map.insert(*k, *k % 1000);
+        }
+        map.reseed(99);
+
+        let strings: IntMap<String> = map.map_values(|v| format!("#{}", v));
+        assert_eq!(strings.len(), map.len());
+        assert_eq!(strings.capacity(), map.capacity());
+        assert_eq!(strings.seed(), map.seed());
+        assert!(strings.assert_count());
+        for (k, v) in map.iter() {
+            assert_eq!(strings.get(*k), Some(&format!("#{}", v)));
+        }
+        // Entries stay in the same buckets.
+        let buckets: Vec<(usize, u64)> = map.iter_with_bucket().map(|(b, k, _)| (b, k)).collect();
+        let string_buckets: Vec<(usize, u64)> =
+            strings.iter_with_bucket().map(|(b, k, _)| (b, k)).collect();
+        assert_eq!(buckets, string_buckets);
+
+        let lengths: IntMap<usize> = strings.into_map_values(|s| s.len());
+        for (k, v) in map.iter() {
+            assert_eq!(lengths.get(*k), Some(&(v.to_string().len() + 1)));
+        }
+        assert_eq!(lengths.len(), map.len());
+    }
+
+    #[test]
+    fn map_filter_even_keys() {
+        let mut map: IntMap<String> = IntMap::new();
+        for i in 0..1000 {
+            map.insert(i, i.to_string());
+        }
+        let original = map.clone();
+
+        let even = map.filter(|k, _| k % 2 == 0);
+        assert_eq!(map, original);
+        assert_eq!(even.len(), 500);
+        assert!(even.assert_count());
+        assert!(even.capacity() < map.capacity());
+        for i in 0..1000 {
+            assert_eq!(even.get(i).is_some(), i % 2 == 0);
+        }
+
+        let none = map.filter(|_, _| false);
+        assert_eq!(none.len(), 0);
+
+        map.reseed(5);
+        let moved = map.into_filtered(|k, v| k % 2 == 0 && v.len() == 3);
+        assert_eq!(moved.len(), 450);
+        assert_eq!(moved.seed(), 5);
+        assert_eq!(moved.get(100).map(|s| s.as_str()), Some("100"));
+        assert_eq!(moved.get(8), None);
+    }
+
+    #[test]
+    fn map_recycle_values() {
+        let mut map: IntMap<Vec<u64>> = IntMap::new();
+        let mut pool: Vec<Vec<u64>> = Vec::new();
+
+        for i in 0..10 {
+            assert!(map.insert_from(i, &mut pool, |buf| buf.extend(0..1000)));
+        }
+        let buffers: Vec<*const u64> = (0..10).map(|i| map.get(i).unwrap().as_ptr()).collect();
+
+        for i in 0..10 {
+            assert!(map.remove_into(i, &mut pool));
+        }
+        assert!(!map.remove_into(10, &mut pool));
+        assert_eq!(pool.len(), 10);
+        assert!(map.is_empty());
+
+        for i in 100..110 {
+            assert!(map.insert_from(i, &mut pool, |buf| {
+                buf.clear();
+                buf.push(i);
+            }));
+        }
+        assert!(pool.is_empty());
+
+        // Every value reuses one of the earlier buffers.
+        for i in 100..110 {
+            let value = map.get(i).unwrap();
+            assert_eq!(value, &vec![i]);
+            assert!(value.capacity() >= 1000);
+            assert!(buffers.contains(&value.as_ptr()));
+        }
+
+        pool.push(Vec::new());
+        assert!(!map.insert_from(100, &mut pool, |_| unreachable!()));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn map_min_capacity_floor() {
+        let mut map: IntMap<u64> = IntMap::with_min_capacity(256);
+        map.set_auto_shrink(true);
+        assert_eq!(map.capacity(), 256);
+
+        for i in 0..10_000 {
+            map.insert(i, i);
+        }
+        assert!(map.capacity() > 256);
+
+        for i in 0..10_000 {
+            map.remove(i);
+            assert!(map.capacity() >= 256);
+        }
+        assert_eq!(map.capacity(), 256);
+
+        for i in 0..10_000 {
+            map.insert(i, i);
+        }
+        map.clear_and_shrink();
+        assert_eq!(map.capacity(), 256);
+
+        assert!(!map.resize_to_size(2));
+        assert_eq!(map.capacity(), 256);
+    }
+
+    #[test]
+    fn map_should_shrink() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert!(!map.should_shrink());
+
+        for i in 0..10_000 {
+            map.insert(i, i);
+        }
+        assert!(!map.should_shrink());
+
+        for i in 0..9_000 {
+            map.remove(i);
+        }
+        assert!(map.should_shrink());
+
+        let capacity = map.capacity();
+        assert!(map.should_shrink());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.len(), 1_000);
+
+        assert!(map.resize_to_size(11));
+        assert!(!map.should_shrink());
+    }
+
+    #[test]
+    fn map_compute_if_absent() {
+        let mut map: IntMap<usize> = IntMap::new();
+
+        for i in 0..100 {
+            assert_eq!(*map.compute_if_absent(i, |m| m.len()), i as usize);
+        }
+
+        *map.compute_if_absent(5, |_| unreachable!()) += 100;
+        assert_eq!(map.get(5), Some(&105));
+        assert_eq!(map.len(), 100);
+    }
+
+    #[cfg(feature = "binary-io")]
+    #[test]
+    fn map_binary_round_trip() {
+        let mut map: IntMap<f64> = IntMap::new();
+        for s in get_random_range(1_000) {
+            map.insert(s, s as f64 / 3.0);
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + map.len() * 16);
+
+        let copy: IntMap<f64> = IntMap::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(copy, map);
+
+        let empty: IntMap<f64> = IntMap::new();
+        let mut bytes: Vec<u8> = Vec::new();
+        empty.write_to(&mut bytes).unwrap();
+        let copy: IntMap<f64> = IntMap::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(copy.len(), 0);
+    }
+
+    #[cfg(feature = "binary-io")]
+    #[test]
+    fn map_binary_truncated() {
+        let map: IntMap<u32> = (0..100).map(|i| (i, i as u32)).collect();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+
+        for len in [0, 7, 8, 12, bytes.len() - 1].iter() {
+            let err = IntMap::<u32>::read_from(&mut &bytes[..*len]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        }
+
+        let mut duplicated: Vec<u8> = Vec::new();
+        duplicated.extend_from_slice(&2u64.to_le_bytes());
+        for _ in 0..2 {
+            duplicated.extend_from_slice(&1u64.to_le_bytes());
+            duplicated.extend_from_slice(&1u32.to_le_bytes());
+        }
+        let err = IntMap::<u32>::read_from(&mut duplicated.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "binary-io")]
+    #[test]
+    fn map_binary_pointer_sized_values() {
+        let mut map: IntMap<isize> = IntMap::new();
+        map.insert(1, -1);
+        map.insert(2, isize::MAX);
+
+        // Pointer sized values are written as 64 bits on every platform.
+        let mut bytes: Vec<u8> = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + map.len() * 16);
+
+        let copy: IntMap<isize> = IntMap::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(copy, map);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        let result = IntMap::<usize>::read_from(&mut bytes.as_slice());
+        if std::mem::size_of::<usize>() < 8 {
+            assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        } else {
+            assert_eq!(result.unwrap().get(1), Some(&usize::MAX));
+        }
+    }
+
+    #[test]
+    fn map_compact_bucket() {
+        let mut map: IntMap<u64> = IntMap::with_capacity(1_000);
+        for i in 0..10 {
+            map.insert(i << 20, i);
+        }
+        for i in 1..10 {
+            map.remove(i << 20);
+        }
+
+        let bucket = map
+            .raw_buckets()
+            .iter()
+            .position(|vals| vals.len() == 1)
+            .unwrap();
+        assert!(map.raw_buckets()[bucket].capacity() >= 10);
+
+        map.compact_bucket(bucket);
+        assert_eq!(map.raw_buckets()[bucket].capacity(), 1);
+        assert_eq!(map.get(0), Some(&0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_compact_bucket_out_of_range() {
+        let mut map: IntMap<u64> = IntMap::new();
+        let capacity = map.capacity();
+        map.compact_bucket(capacity);
+    }
+
+    #[test]
+    fn map_nth_entry() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert_eq!(map.nth_entry(0), None);
+
+        for s in get_random_range(1_000) {
+            map.insert(s, s);
+        }
+
+        for k in 0..map.len() + 10 {
+            assert_eq!(map.nth_entry(k), map.iter().nth(k).map(|(k, v)| (*k, v)));
+        }
+
+        let indices = [999, 0, 500, 500, 1_000];
+        let sample = map.sample(&indices);
+        for (n, entry) in indices.iter().zip(sample) {
+            assert_eq!(entry, map.nth_entry(*n));
+        }
+        assert!(map.sample(&[]).is_empty());
+    }
+
+    #[test]
+    fn map_nested() {
+        let mut matrix: IntMap<IntMap<u64>> = IntMap::new();
+
+        for row in 0..10 {
+            for col in (0..100).step_by(row as usize + 1) {
+                assert!(matrix.insert_nested(row, col, row * col));
+            }
+        }
+        assert!(!matrix.insert_nested(0, 0, 1));
+        assert_eq!(matrix.len(), 10);
+        assert_eq!(matrix.get(1).map(|cols| cols.len()), Some(50));
+
+        assert_eq!(matrix.get_nested(0, 0), Some(&0));
+        assert_eq!(matrix.get_nested(3, 96), Some(&288));
+        assert_eq!(matrix.get_nested(3, 97), None);
+        assert_eq!(matrix.get_nested(10, 0), None);
+        assert!(!matrix.contains_key(10));
+    }
+
+    #[test]
+    fn map_distribution_quality() {
+        let empty: IntMap<u64> = IntMap::new();
+        assert_eq!(empty.distribution_quality(), 1.0);
+
+        for count in [10, 1_000, 100_000].iter() {
+            let mut map: IntMap<u64> = IntMap::new();
+            for s in get_random_range(*count) {
+                map.insert(s, s);
+            }
+
+            let quality = map.distribution_quality();
+            assert!(quality > 0.5 && quality < 2.0, "{}: {}", count, quality);
+        }
+
+        let clustered: IntMap<u64> = (0..10_000).map(|i| (i << 32, i)).collect();
+        assert!(clustered.distribution_quality() > 100.0);
+    }
+
+    #[test]
+    fn map_insert_slice_last_wins() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(0, 100);
+        map.insert(1_000, 100);
+
+        let pairs: Vec<(u64, u64)> = (0..300).map(|i| (i % 100, i)).collect();
+        map.insert_slice_last_wins(&pairs);
+
+        assert_eq!(map.len(), 101);
+        for k in 0..100 {
+            assert_eq!(map.get(k), Some(&(k + 200)));
+        }
+        assert_eq!(map.get(1_000), Some(&100));
+
+        let expected: std::collections::HashMap<u64, u64> = pairs.iter().cloned().collect();
+        for (k, v) in expected {
+            assert_eq!(map.get(k), Some(&v));
+        }
+    }
+
+    #[test]
+    fn map_spare_capacity() {
+        let mut map: IntMap<u64> = IntMap::new();
+        let mut key = 0;
+
+        for _ in 0..10 {
+            let capacity = map.capacity();
+            let spare = map.spare_capacity();
+            assert!(spare > 0);
+
+            for i in 0..spare {
+                assert_eq!(map.spare_capacity(), spare - i);
+                map.insert(key, key);
+                key += 1;
+            }
+            assert_eq!(map.spare_capacity(), 0);
+            assert_eq!(map.capacity(), capacity);
+
+            // Keep inserting until the map grows.
+            while map.capacity() == capacity {
+                map.insert(key, key);
+                key += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn map_from_columns() {
+        let keys: Vec<u64> = (0..1_000).collect();
+        let values: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+
+        let map = IntMap::from_columns(&keys, values).unwrap();
+        assert_eq!(map.len(), 1_000);
+        for k in keys.iter() {
+            assert_eq!(map.get(*k), Some(&k.to_string()));
+        }
+
+        let empty: IntMap<u64> = IntMap::from_columns(&[], Vec::new()).unwrap();
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[test]
+    fn map_from_columns_length_mismatch() {
+        let err = IntMap::from_columns(&[1, 2, 3], vec![1, 2]).unwrap_err();
+        assert_eq!(err, LengthMismatch { keys: 3, values: 2 });
+        assert_eq!(err.to_string(), "3 keys but 2 values");
+
+        let err = IntMap::from_columns(&[], vec![1]).unwrap_err();
+        assert_eq!(err, LengthMismatch { keys: 0, values: 1 });
+    }
+
+    #[test]
+    fn map_from_columns_duplicate_keys() {
+        let map = IntMap::from_columns(&[5, 6, 5, 5], vec![1, 2, 3, 4]).unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(5), Some(&1));
+        assert_eq!(map.get(6), Some(&2));
+    }
+
+    #[cfg(feature = "entry-generations")]
+    #[test]
+    fn map_entry_generations() {
+        let mut map: IntMap<u64> = IntMap::new();
+        assert_eq!(map.generation_of(1), None);
+        assert_eq!(map.bump_generation(1), None);
+
+        map.insert(1, 10);
+        map.insert(2, 20);
+        assert_eq!(map.generation_of(1), Some(1));
+        assert_eq!(map.generation_of(2), Some(1));
+
+        // Inserting a present key keeps the first value and the generation.
+        assert!(!map.insert(1, 11));
+        assert_eq!(map.generation_of(1), Some(1));
+
+        map.insert_mut(1, 12);
+        assert_eq!(map.generation_of(1), Some(2));
+        map.insert_counting(1, 13);
+        assert_eq!(map.generation_of(1), Some(3));
+        if let Entry::Occupied(mut entry) = map.entry(1) {
+            entry.insert(14);
+        }
+        assert_eq!(map.generation_of(1), Some(4));
+
+        *map.get_mut(1).unwrap() += 1;
+        assert_eq!(map.generation_of(1), Some(4));
+
+        assert_eq!(map.bump_generation(1), Some(5));
+        assert_eq!(map.generation_of(1), Some(5));
+        assert_eq!(map.get(1), Some(&15));
+        assert_eq!(map.generation_of(2), Some(1));
+
+        // Removed keys have no generation, but continue from the old one when reinserted.
+        map.remove(1);
+        assert_eq!(map.generation_of(1), None);
+        map.insert(1, 0);
+        assert_eq!(map.generation_of(1), Some(6));
+
+        // Resizes keep the generations, new keys start above every removed one.
+        for i in 3..1_000 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.generation_of(1), Some(6));
+        assert_eq!(map.generation_of(999), Some(6));
+
+        // Removing through any path forgets the key without reusing its generations.
+        for _ in 0..3 {
+            map.bump_generation(500);
+        }
+        let handle = map.generation_of(500).unwrap();
+        map.retain(|k, _| k != 500);
+        map.insert(500, 0);
+        assert!(map.generation_of(500).unwrap() > handle);
+
+        let handle = map.generation_of(500).unwrap();
+        map.clear();
+        map.insert(500, 0);
+        assert!(map.generation_of(500).unwrap() > handle);
+
+        let handle = map.generation_of(500).unwrap();
+        if let Entry::Occupied(entry) = map.entry(500) {
+            entry.remove();
+        }
+        map.insert(500, 0);
+        assert!(map.generation_of(500).unwrap() > handle);
+
+        let handle = map.generation_of(500).unwrap();
+        map.drain().for_each(drop);
+        map.insert(500, 0);
+        assert!(map.generation_of(500).unwrap() > handle);
+    }
+
+    #[test]
+    fn map_extend_exact_size_resizes_once() {
+        use std::sync::{Arc, Mutex};
+
+        let keys = get_random_range(100_000);
+
+        let resizes = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&resizes);
+        let mut map: IntMap<u64> = IntMap::new();
+        map.on_resize(move |_, _| *counter.lock().unwrap() += 1);
+
+        map.extend(keys.iter().map(|k| (*k, *k)));
+        assert_eq!(map.len(), keys.len());
+        assert!(*resizes.lock().unwrap() <= 1);
+
+        // Keys beyond the size hint still fit, by growing as usual.
+        let capacity = map.capacity();
+        map.extend((0..1_000_000).filter(|k| k % 2 == 0).map(|k| (k, k)));
+        assert!(map.capacity() > capacity);
+        assert!(map.load_rate() <= 70.0);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn map_collect_exact_size_resizes_once() {
+        let keys = get_random_range(100_000);
+
+        let map: IntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+        assert_eq!(map.len(), keys.len());
+        assert!(map.stats().resizes <= 1);
+    }
+
+    #[test]
+    fn map_cursor_mut_remove_every_other() {
+        let mut map: IntMap<u64> = IntMap::new();
+        for s in get_random_range(1_000) {
+            map.insert(s, 0);
+        }
+        // Make some buckets long, so removals happen in the middle of buckets.
+        for i in 1..50 {
+            map.insert(i << 40, 0);
+        }
+        let count = map.len();
+
+        let mut visited = Vec::new();
+        let mut removed = Vec::new();
+        let mut cursor = map.cursor_mut();
+        assert_eq!(cursor.remove_current(), None);
+        while let Some((k, v)) = cursor.next() {
+            *v += 1;
+            visited.push(k);
+            if visited.len() % 2 == 0 {
+                assert_eq!(cursor.remove_current(), Some(1));
+                assert_eq!(cursor.remove_current(), None);
+                removed.push(k);
+            }
+        }
+        assert!(cursor.next().is_none());
+        assert_eq!(cursor.remove_current(), None);
+
+        // Every entry was visited exactly once.
+        assert_eq!(visited.len(), count);
+        visited.sort();
+        visited.dedup();
+        assert_eq!(visited.len(), count);
+
+        assert_eq!(map.len(), count - count / 2);
+        for k in removed.iter() {
+            assert!(!map.contains_key(*k));
+        }
+        assert!(map.values().all(|v| *v == 1));
+        assert_eq!(map.iter().count(), map.len());
+    }
+
+    #[test]
+    fn map_preallocate_buckets() {
+        let mut map: IntMap<u64> = IntMap::with_capacity(1_000);
+        for i in 0..500 {
+            map.insert(i * 3, i);
+        }
+        for i in 0..20 {
+            map.insert(i << 40, i);
+        }
+        let before = map.clone();
+
+        map.preallocate_buckets(4);
+        assert!(map.raw_buckets().iter().all(|vals| vals.capacity() >= 4));
+        assert_eq!(map, before);
+        assert_eq!(map.len(), before.len());
+        assert_eq!(map.capacity(), before.capacity());
+
+        // The room is already there, inserting into a short bucket does not reallocate it.
+        assert!(map.spare_capacity() > 0);
+        let ptrs: Vec<_> = map.raw_buckets().iter().map(|vals| vals.as_ptr()).collect();
+        map.insert(1, 1);
+        let after: Vec<_> = map.raw_buckets().iter().map(|vals| vals.as_ptr()).collect();
+        assert_eq!(after, ptrs);
+    }
+
+    #[test]
+    fn map_deterministic() {
+        let keys = get_random_range(10_000);
+
+        let mut a: IntMap<u64> = IntMap::deterministic();
+        let mut b: IntMap<u64> = IntMap::deterministic();
+        for k in keys.iter() {
+            a.insert(*k, *k);
+        }
+        for k in keys.iter() {
+            b.insert(*k, *k);
+        }
+        assert_eq!(a.seed(), 0);
+        assert_eq!(b.seed(), 0);
+
+        let buckets = |map: &IntMap<u64>| -> Vec<(usize, u64)> {
+            map.iter_with_bucket().map(|(ix, k, _)| (ix, k)).collect()
+        };
+        assert_eq!(buckets(&a), buckets(&b));
+
+        // Every key lands in the bucket the plain multiplicative hash gives it.
+        let mask = a.capacity() as u64 - 1;
+        for (ix, k) in buckets(&a) {
+            assert_eq!(ix as u64, 11400714819323198549u64.wrapping_mul(k) & mask);
+        }
+    }
+
+    #[test]
+    fn map_occupancy_summary() {
+        let mut map: IntMap<u64> = IntMap::deterministic();
+        assert_eq!(map.occupancy_summary(), (map.capacity(), 0, 0));
+
+        // Without a seed, keys only differing in their high bits share a bucket.
+        for i in 1..=5 {
+            map.insert(i, i);
+        }
+        map.insert((1 << 40) + 1, 6);
+        map.insert((2 << 40) + 1, 7);
+        map.insert((3 << 40) + 2, 8);
+
+        let (empty, singleton, multi) = map.occupancy_summary();
+        assert_eq!((empty, singleton, multi), (map.capacity() - 5, 3, 2));
+        assert_eq!(empty + singleton + multi, map.capacity());
+        assert_eq!((singleton + multi) as u64, map.load());
+    }
+
+    #[test]
+    fn map_values_mut_sorted() {
+        let keys = get_random_range(1_000);
+        let mut map: IntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+
+        // Running sum of the values in key order, which depends on the order of visits.
+        let mut seen = Vec::new();
+        let mut sum = 0u64;
+        for (k, v) in map.values_mut_sorted() {
+            seen.push(k);
+            sum = sum.wrapping_add(*v);
+            *v = sum;
+        }
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(seen, sorted);
+
+        let mut sum = 0u64;
+        for k in sorted.iter() {
+            sum = sum.wrapping_add(*k);
+            assert_eq!(map.get(*k), Some(&sum));
+        }
+        assert_eq!(map.len(), keys.len());
+    }
+
+    #[test]
+    fn map_with_capacity_and_load() {
+        for &max_load in [10u8, 25, 50, 70, 90, 100].iter() {
+            let expected = 1_000;
+            let mut map: IntMap<u64> = IntMap::with_capacity_and_load(expected, max_load);
+            let capacity = map.capacity();
+
+            for i in 0..expected as u64 {
+                map.insert(i, i);
+            }
+            assert_eq!(map.capacity(), capacity);
+            assert!(map.load_rate() <= max_load as f64);
+
+            // Growth keeps the configured load rate.
+            map.reserve(9 * expected);
+            let capacity = map.capacity();
+            for i in 0..(10 * expected) as u64 {
+                map.insert(i, i);
+            }
+            assert_eq!(map.capacity(), capacity);
+            assert!(map.load_rate() <= max_load as f64);
+            assert_eq!(map.spare_capacity(), {
+                let max_count = (map.capacity() * (max_load as usize + 1) - 1) / 100;
+                max_count - map.len()
+            });
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_with_capacity_and_load_zero() {
+        let _map: IntMap<u64> = IntMap::with_capacity_and_load(10, 0);
+    }
+
+    #[test]
+    fn map_tagged_iter() {
+        let mut left: IntMap<u64> = IntMap::new();
+        let mut right: IntMap<u64> = IntMap::new();
+        for i in 0..1_000 {
+            left.insert(i, i);
+        }
+        for i in 500..2_000 {
+            right.insert(i, i + 10_000);
+        }
+
+        let mut tagged: Vec<_> = left.tagged_iter(&right).collect();
+        assert_eq!(tagged.len(), 2_000);
+        tagged.sort_by_key(|t| t.0);
+
+        for (i, (k, v, origin)) in tagged.into_iter().enumerate() {
+            assert_eq!(k, i as u64);
+            if k < 500 {
+                assert_eq!((*v, origin), (k, Origin::Left));
+            } else if k < 1_000 {
+                assert_eq!((*v, origin), (k, Origin::Both));
+            } else {
+                assert_eq!((*v, origin), (k + 10_000, Origin::Right));
+            }
+        }
+
+        let empty: IntMap<u64> = IntMap::new();
+        assert!(left.tagged_iter(&empty).all(|t| t.2 == Origin::Left));
+        assert!(empty.tagged_iter(&right).all(|t| t.2 == Origin::Right));
+    }
+
+    #[test]
+    fn map_rehash_if_degraded() {
+        let mut clustered: IntMap<u64> = IntMap::deterministic();
+        for i in 0..1_000 {
+            clustered.insert(i << 32, i);
+        }
+        let before = clustered.clone();
+        let quality = clustered.distribution_quality();
+        assert!(quality > 10.0);
+
+        assert!(clustered.rehash_if_degraded(4.0));
+        assert!(clustered.distribution_quality() < quality);
+        assert!(clustered.distribution_quality() <= 4.0);
+        assert_ne!(clustered.seed(), before.seed());
+        assert_eq!(clustered, before);
+
+        // Once healthy, checking again does nothing.
+        let seed = clustered.seed();
+        assert!(!clustered.rehash_if_degraded(4.0));
+        assert_eq!(clustered.seed(), seed);
+
+        // Keys only differing in their highest bits are recovered from as well.
+        let mut high_bits: IntMap<u64> = (0..2_000).map(|i| (i << 44, i)).collect();
+        assert_eq!(high_bits.max_bucket_len(), 2_000);
+        assert!(high_bits.rehash_if_degraded(4.0));
+        assert!(high_bits.distribution_quality() <= 4.0);
+        for i in 0..2_000 {
+            assert_eq!(high_bits.get(i << 44), Some(&i));
+        }
+
+        let mut spread: IntMap<u64> = IntMap::deterministic();
+        for s in get_random_range(1_000) {
+            spread.insert(s, s);
+        }
+        let ptrs: Vec<_> = spread
+            .raw_buckets()
+            .iter()
+            .map(|vals| vals.as_ptr())
+            .collect();
+        assert!(!spread.rehash_if_degraded(4.0));
+        assert_eq!(spread.seed(), 0);
+        let after: Vec<_> = spread
+            .raw_buckets()
+            .iter()
+            .map(|vals| vals.as_ptr())
+            .collect();
+        assert_eq!(after, ptrs);
+    }
+
+    #[test]
+    fn map_filter_collect_remove() {
+        let keys = get_random_range(1_000);
+        let mut map: IntMap<u64> = keys.iter().map(|k| (*k, *k)).collect();
+        // Long buckets, so several entries of one bucket are removed.
+        for i in 1..50 {
+            map.insert(i << 40, i << 40);
+        }
+        let before = map.clone();
+
+        let mut removed = map.filter_collect_remove(|k, v| k == *v && k % 3 == 0);
+        removed.sort();
+        let mut expected: Vec<_> = before
+            .iter()
+            .filter(|(k, _)| **k % 3 == 0)
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        expected.sort();
+        assert_eq!(removed, expected);
+
+        assert_eq!(map.len(), before.len() - removed.len());
+        assert_eq!(map.iter().count(), map.len());
+        for (k, v) in before.iter() {
+            assert_eq!(map.get(*k), if *k % 3 == 0 { None } else { Some(v) });
+        }
+
+        assert!(map.filter_collect_remove(|k, _| k % 3 == 0).is_empty());
+
+        let rest: Vec<u64> = map.keys_copied().collect();
+        map.remove_many_from_iter(rest.into_iter().chain(Some(3)));
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn map_capacity_report() {
+        let empty: IntMap<u64> = IntMap::new();
+        let report = empty.capacity_report();
+        assert_eq!(report.count, 0);
+        assert_eq!(report.total_slot_capacity, 0);
+        assert_eq!(report.load_rate, 0.0);
+
+        let mut map: IntMap<u64> = IntMap::new();
+        for s in get_random_range(1_000) {
+            map.insert(s, s);
+        }
+        let keys: Vec<u64> = map.keys_copied().take(500).collect();
+        for k in keys {
+            map.remove(k);
+        }
+
+        let report: CapacityReport = map.capacity_report();
+        assert_eq!(report.count, map.len());
+        assert_eq!(report.bucket_count, map.capacity());
+        assert_eq!(report.load_rate, map.load_rate());
+        // Removed entries keep their slots.
+        assert!(report.count <= report.total_slot_capacity);
+        assert!(report.total_slot_capacity >= 1_000);
+        assert!(report.bytes_estimate >= report.total_slot_capacity * 16);
+        assert!(report.bytes_estimate > empty.capacity_report().bytes_estimate);
+    }
+
+    #[test]
+    fn entry_or_try_insert_with() {
+        let mut map: IntMap<u64> = IntMap::new();
+        map.insert(1, 10);
+        let generation = map.generation();
+
+        // Occupied: the closure does not run, the existing value is returned.
+        let res: Result<_, &str> = map.entry(1).or_try_insert_with(|| unreachable!());
+        *res.unwrap() += 1;
+        assert_eq!(map.get(1), Some(&11));
+        assert_eq!(map.len(), 1);
+
+        // Vacant and failing: nothing is inserted.
+        let res = map.entry(2).or_try_insert_with(|| Err("no value"));
+        assert_eq!(res, Err("no value"));
+        assert_eq!(map.len(), 1);
+        assert!(!map.contains_key(2));
+        assert_eq!(map.generation(), generation);
+        assert!(map.assert_count());
+
+        // Vacant and succeeding: the value is inserted.
+        let res: Result<_, &str> = map.entry(2).or_try_insert_with(|| Ok(20));
+        assert_eq!(res, Ok(&mut 20));
+        assert_eq!(map.get(2), Some(&20));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn map_swap() {
+        let mut map: IntMap<String> = IntMap::new();
+        for i in 0..100 {
+            map.insert(i, i.to_string());
+        }
+        // Keys sharing a bucket with 0.
+        map.insert(1 << 40, "a".to_string());
+        map.insert(2 << 40, "b".to_string());
+        assert!(map.keys_collide(1 << 40, 2 << 40));
+
+        // Both present, in different buckets and in the same one.
+        assert!(map.swap(3, 70));
+        assert_eq!(map.get(3).unwrap(), "70");
+        assert_eq!(map.get(70).unwrap(), "3");
+        assert!(map.swap(2 << 40, 1 << 40));
+        assert_eq!(map.get(1 << 40).unwrap(), "b");
+        assert_eq!(map.get(2 << 40).unwrap(), "a");
+
+        // One missing, either way round.
+        let before = map.clone();
+        assert!(!map.swap(5, 1_000));
+        assert!(!map.swap(1_000, 5));
+        assert!(!map.swap(3 << 40, 1 << 40));
+        assert_eq!(map, before);
+
+        // Same key.
+        assert!(map.swap(5, 5));
+        assert!(!map.swap(1_000, 1_000));
+        assert_eq!(map, before);
+        assert_eq!(map.len(), 102);
+    }
+
+    #[test]
+    fn map_iter_sorted_rle() {
+        let mut map: IntMap<u64> = IntMap::new();
+        for (k, v) in [1, 1, 1, 2, 3, 3, 1, 1, 1].iter().enumerate() {
+            map.insert(k as u64 + 1, *v);
+        }
+
+        let runs: Vec<_> = map.iter_sorted_rle().collect();
+        assert_eq!(runs, [(1, 3, &1), (4, 4, &2), (5, 6, &3), (7, 9, &1)]);
+
+        // A gap breaks a run of equal values.
+        map.remove(8);
+        let runs: Vec<_> = map.iter_sorted_rle().collect();
+        assert_eq!(
+            runs,
+            [(1, 3, &1), (4, 4, &2), (5, 6, &3), (7, 7, &1), (9, 9, &1)]
+        );
+
+        // Runs end at the largest key instead of overflowing.
+        let mut edge: IntMap<u64> = IntMap::new();
+        edge.insert(u64::MAX, 0);
+        edge.insert(0, 0);
+        let runs: Vec<_> = edge.iter_sorted_rle().collect();
+        assert_eq!(runs, [(0, 0, &0), (u64::MAX, u64::MAX, &0)]);
+
+        assert_eq!(IntMap::<u64>::new().iter_sorted_rle().count(), 0);
+    }
+
+    #[cfg(feature = "track-removals")]
+    #[test]
+    fn map_recent_removals() {
+        let mut map: IntMap<u64> = IntMap::with_removal_history(4);
+        for i in 0..20 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.recent_removals().count(), 0);
+
+        // Missing keys are not recorded.
+        map.remove(100);
+        map.remove(1);
+        map.remove(1);
+        map.remove(2);
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [1, 2]);
+
+        // Every way of removing a single entry is recorded.
+        map.remove_if(3, |_| true);
+        match map.entry(4) {
+            Entry::Occupied(entry) => {
+                entry.remove();
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        // Once full the oldest keys are dropped, the rest stay in removal order.
+        map.retain(|k, _| k != 5);
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [2, 3, 4, 5]);
+        for k in 6..9 {
+            map.remove(k);
+        }
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [5, 6, 7, 8]);
+        map.remove(9);
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [6, 7, 8, 9]);
+        for k in [12, 10, 11].iter() {
+            map.remove(*k);
+        }
+        assert_eq!(map.recent_removals().collect::<Vec<_>>(), [9, 12, 10, 11]);
+
+        let mut default: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+        for i in 0..100 {
+            default.remove(i);
+        }
+        assert!(default.recent_removals().eq(84..100));
+
+        let mut disabled: IntMap<u64> = IntMap::with_removal_history(0);
+        disabled.insert(1, 1);
+        disabled.remove(1);
+        assert_eq!(disabled.recent_removals().count(), 0);
     }
 }
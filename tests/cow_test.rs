@@ -0,0 +1,83 @@
+extern crate intmap;
+
+use intmap::{CowIntMap, IntMap};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cow_insert_get_remove() {
+        let count = 20_000;
+        let mut map: CowIntMap<u64> = CowIntMap::new();
+
+        for i in 0..count {
+            assert!(map.insert(i, i));
+        }
+        assert!(!map.insert(0, 1));
+        assert_eq!(map.len(), count as usize);
+
+        for i in 0..count {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        for i in 0..count {
+            assert_eq!(map.remove(i), Some(i));
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn cow_clone_is_shared_until_mutated() {
+        let map = (0..1_000).map(|i| (i, i)).collect::<IntMap<u64>>();
+        let map = CowIntMap::from(map);
+
+        let mut clone = map.clone();
+        assert!(clone.ptr_eq(&map));
+
+        *clone.get_mut(1).unwrap() += 1;
+        assert!(!clone.ptr_eq(&map));
+    }
+
+    #[test]
+    fn cow_mutating_clone_keeps_original() {
+        let mut original: CowIntMap<u64> = CowIntMap::new();
+        for i in 0..1_000 {
+            original.insert(i, i);
+        }
+
+        let mut clone = original.clone();
+        *clone.get_mut(1).unwrap() = 100;
+        clone.remove(2);
+        for i in 1_000..5_000 {
+            clone.insert(i, i);
+        }
+
+        assert_eq!(original.len(), 1_000);
+        assert_eq!(original.get(1), Some(&1));
+        assert_eq!(original.get(2), Some(&2));
+        assert_eq!(original.get(1_000), None);
+
+        assert_eq!(clone.len(), 4_999);
+        assert_eq!(clone.get(1), Some(&100));
+        assert_eq!(clone.get(2), None);
+        assert_eq!(clone.get(4_999), Some(&4_999));
+    }
+
+    #[test]
+    fn cow_mutating_original_keeps_clone() {
+        let mut original: CowIntMap<String> = CowIntMap::new();
+        for i in 0..100 {
+            original.insert(i, format!("{}", i));
+        }
+
+        let clone = original.clone();
+        original.get_mut(5).unwrap().push('!');
+        original.remove(6);
+
+        assert_eq!(clone.get(5).map(|s| s.as_str()), Some("5"));
+        assert_eq!(clone.get(6).map(|s| s.as_str()), Some("6"));
+        assert_eq!(original.get(5).map(|s| s.as_str()), Some("5!"));
+        assert_eq!(original.get(6), None);
+    }
+}
@@ -0,0 +1,93 @@
+extern crate intmap;
+
+use intmap::{CapacityError, FixedIntMap};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_insert_up_to_capacity() {
+        let mut map: FixedIntMap<u64, 1000> = FixedIntMap::new();
+        assert_eq!(map.capacity(), 1000);
+
+        for i in 0..1000 {
+            assert_eq!(map.insert(i, i), Ok(true));
+        }
+        assert_eq!(map.len(), 1000);
+
+        for i in 0..1000 {
+            assert_eq!(map.get(i), Some(&i));
+        }
+
+        assert_eq!(
+            map.insert(1000, 1),
+            Err(CapacityError {
+                key: 1000,
+                value: 1
+            })
+        );
+        // Present keys are still reported as such when full.
+        assert_eq!(map.insert(5, 0), Ok(false));
+        assert_eq!(map.len(), 1000);
+    }
+
+    #[test]
+    fn fixed_full_power_of_two() {
+        let mut map: FixedIntMap<u64, 8> = FixedIntMap::new();
+
+        for i in 0..8 {
+            assert_eq!(map.insert(i << 40, i), Ok(true));
+        }
+        assert!(map.insert(100, 0).is_err());
+        assert_eq!(map.get(100), None);
+
+        for i in 0..8 {
+            assert_eq!(map.get(i << 40), Some(&i));
+        }
+    }
+
+    #[test]
+    fn fixed_remove_keeps_colliding_keys_reachable() {
+        let mut map: FixedIntMap<u64, 64> = FixedIntMap::new();
+
+        // All of these hash to the same slot.
+        for i in 0..20 {
+            map.insert(i << 40, i).unwrap();
+        }
+        for i in 0..20 {
+            map.insert(1_000 + i, i).unwrap();
+        }
+
+        for i in (0..20).step_by(2) {
+            assert_eq!(map.remove(i << 40), Some(i));
+        }
+        assert_eq!(map.remove(0), None);
+        assert_eq!(map.len(), 30);
+
+        for i in 0..20 {
+            let expected = if i % 2 == 0 { None } else { Some(&i) };
+            assert_eq!(map.get(i << 40), expected);
+            assert_eq!(map.get(1_000 + i), Some(&i));
+        }
+
+        // Freed slots can be used again.
+        for i in 0..34 {
+            map.insert(5_000 + i, i).unwrap();
+        }
+        assert!(map.insert(6_000, 0).is_err());
+    }
+
+    #[test]
+    fn fixed_clear() {
+        let mut map: FixedIntMap<u64, 4> = FixedIntMap::new();
+
+        for i in 0..4 {
+            map.insert(i, i).unwrap();
+        }
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.insert(10, 10), Ok(true));
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "rayon")]
+
+extern crate intmap;
+extern crate rayon;
+
+use intmap::IntMap;
+use rayon::prelude::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_par_from_slice() {
+        // Every key occurs three times, spread over the whole slice.
+        let pairs: Vec<(u64, u64)> = (0..30_000).map(|i| ((i * 7919) % 10_000, i)).collect();
+
+        let mut sequential: IntMap<u64> = IntMap::new();
+        sequential.insert_slice_last_wins(&pairs);
+
+        let parallel = IntMap::par_from_slice(&pairs);
+        assert_eq!(parallel.len(), sequential.len());
+        assert_eq!(parallel, sequential);
+        assert!(parallel.assert_count());
+
+        let empty: IntMap<u64> = IntMap::par_from_slice(&[]);
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[test]
+    fn map_par_values_mut() {
+        let mut parallel: IntMap<u64> = (0..10_000).map(|i| (i, i)).collect();
+        let mut sequential = parallel.clone();
+
+        (&mut parallel).into_par_iter().for_each(|v| *v *= *v);
+        for v in sequential.values_mut() {
+            *v *= *v;
+        }
+
+        assert_eq!(parallel.len(), sequential.len());
+        assert_eq!(parallel, sequential);
+        assert_eq!((&mut parallel).into_par_iter().count(), 10_000);
+    }
+}
@@ -0,0 +1,85 @@
+extern crate intmap;
+extern crate rand;
+
+use intmap::{PerfectHashError, PerfectIntMap};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng, StdRng};
+
+    fn get_random_range(count: usize) -> Vec<u64> {
+        let mut vec = Vec::new();
+        let mut rng = StdRng::from_seed(&[4, 2, 4, 2]);
+
+        for _ in 0..count {
+            vec.push(rng.gen::<u64>());
+        }
+
+        vec.sort();
+        vec.dedup();
+
+        vec
+    }
+
+    #[test]
+    fn perfect_get_all_keys() {
+        let keys = get_random_range(10_000);
+        let map = PerfectIntMap::from_keys(&keys, |k| k.wrapping_mul(3)).unwrap();
+
+        assert_eq!(map.len(), keys.len());
+        assert!(map.capacity() >= keys.len());
+        assert!(map.capacity().is_power_of_two());
+        for k in keys.iter() {
+            assert_eq!(map.get(*k), Some(&k.wrapping_mul(3)));
+        }
+        for k in 0..1_000 {
+            assert!(!map.contains_key(k));
+        }
+        assert_eq!(map.iter().count(), keys.len());
+    }
+
+    #[test]
+    fn perfect_clustered_keys() {
+        let keys: Vec<u64> = (0..10_000).map(|i| i << 40).chain(1..10_000).collect();
+        let map = PerfectIntMap::from_keys(&keys, |k| k).unwrap();
+
+        for k in keys.iter() {
+            assert_eq!(map.get(*k), Some(k));
+        }
+    }
+
+    #[test]
+    fn perfect_small_and_empty() {
+        let empty: PerfectIntMap<u64> = PerfectIntMap::from_keys(&[], |k| k).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+
+        let one = PerfectIntMap::from_keys(&[42], |_| "answer").unwrap();
+        assert_eq!(one.get(42), Some(&"answer"));
+        assert_eq!(one.get(0), None);
+    }
+
+    #[test]
+    fn perfect_duplicate_key() {
+        let result = PerfectIntMap::from_keys(&[1, 2, 3, 2], |k| k);
+        assert_eq!(result.unwrap_err(), PerfectHashError::DuplicateKey(2));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn perfect_single_comparison_per_get() {
+        let keys = get_random_range(10_000);
+        let map = PerfectIntMap::from_keys(&keys, |k| k).unwrap();
+
+        for k in keys.iter() {
+            assert!(map.contains_key(*k));
+        }
+
+        let stats = map.stats();
+        assert_eq!(stats.gets, keys.len() as u64);
+        assert_eq!(stats.comparisons, keys.len() as u64);
+        assert_eq!(stats.comparisons_per_get(), 1.0);
+    }
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "serde")]
+
+extern crate bincode;
+extern crate intmap;
+extern crate serde;
+extern crate serde_json;
+
+use intmap::IntMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::fmt::Debug;
+
+    // Round-trips `map` through a self-describing and a compact format.
+    fn assert_serde_roundtrip<V>(map: &IntMap<V>)
+    where
+        V: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let json = serde_json::to_string(map).unwrap();
+        let copy: IntMap<V> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(&copy, map);
+        assert_eq!(copy.seed(), map.seed());
+
+        let bytes = bincode::serialize(map).unwrap();
+        let copy: IntMap<V> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(&copy, map);
+        assert_eq!(copy.seed(), map.seed());
+    }
+
+    #[test]
+    fn map_serde_roundtrip_value_types() {
+        assert_serde_roundtrip::<u64>(&IntMap::new());
+        assert_serde_roundtrip(&(0..100).map(|i| (i, i * i)).collect::<IntMap<u64>>());
+        assert_serde_roundtrip(&(0..100).map(|i| (i, i as f64 / 3.0)).collect::<IntMap<_>>());
+        assert_serde_roundtrip(&(0..100).map(|i| (i, ())).collect::<IntMap<()>>());
+        assert_serde_roundtrip(
+            &(0..100)
+                .map(|i| (u64::MAX - i, format!("{}", i)))
+                .collect::<IntMap<String>>(),
+        );
+        assert_serde_roundtrip(
+            &(0..100)
+                .map(|i| (i, if i % 2 == 0 { Some(vec![i; 3]) } else { None }))
+                .collect::<IntMap<Option<Vec<u64>>>>(),
+        );
+
+        let mut nested: IntMap<IntMap<u64>> = IntMap::new();
+        for i in 0..100 {
+            nested.insert_nested(i % 10, i, i * 2);
+        }
+        assert_serde_roundtrip(&nested);
+
+        #[cfg(feature = "serialize-config")]
+        {
+            let mut seeded: IntMap<u64> = (0..100).map(|i| (i, i)).collect();
+            seeded.reseed(0x5eed);
+            assert_serde_roundtrip(&seeded);
+        }
+    }
+
+    #[test]
+    fn map_serde_round_trip() {
+        let map: IntMap<String> = (0..100).map(|i| (i, format!("{}", i))).collect();
+
+        let json = serde_json::to_string(&map).unwrap();
+        let copy: IntMap<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(copy, map);
+    }
+
+    #[test]
+    fn map_serde_borrowed_values() {
+        let map: IntMap<&str> = (0..100).map(|i| (i, "borrowed")).collect();
+        let json = serde_json::to_string(&map).unwrap();
+
+        let copy: IntMap<&str> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy, map);
+
+        // Every value points into the input instead of into a copy.
+        let input = json.as_bytes().as_ptr_range();
+        for value in copy.values() {
+            assert!(input.contains(&value.as_ptr()));
+        }
+    }
+
+    #[cfg(not(feature = "serialize-config"))]
+    #[test]
+    fn map_serde_duplicate_key() {
+        let err = serde_json::from_str::<IntMap<u64>>(r#"{"1": 2, "1": 3}"#).unwrap_err();
+        assert!(err.to_string().contains("duplicate key 1"));
+    }
+
+    #[cfg(feature = "serialize-config")]
+    #[test]
+    fn map_serde_config_round_trip() {
+        let mut map: IntMap<u64> = IntMap::with_capacity_and_load(100, 25);
+        map.reseed(0xdead_beef);
+        for i in 0..100 {
+            map.insert(i, i * 2);
+        }
+
+        let json = serde_json::to_string(&map).unwrap();
+        let copy: IntMap<u64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.seed(), 0xdead_beef);
+        assert_eq!(copy.capacity(), map.capacity());
+        assert_eq!(copy.len(), map.len());
+        assert_eq!(copy, map);
+
+        let buckets = |m: &IntMap<u64>| {
+            let mut v: Vec<_> = m.iter_with_bucket().map(|(b, k, _)| (k, b)).collect();
+            v.sort();
+            v
+        };
+        assert_eq!(buckets(&copy), buckets(&map));
+    }
+
+    #[cfg(feature = "serialize-config")]
+    #[test]
+    fn map_serde_config_defaults() {
+        let copy: IntMap<u64> = serde_json::from_str(r#"{"entries": {"1": 2, "3": 4}}"#).unwrap();
+        assert_eq!(copy.seed(), IntMap::<u64>::new().seed());
+        assert_eq!(copy.get(3), Some(&4));
+
+        let err = serde_json::from_str::<IntMap<u64>>(r#"{"max_load": 0, "entries": {}}"#);
+        assert!(err.is_err());
+        let err = serde_json::from_str::<IntMap<u64>>(r#"{"seed": 1}"#);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("missing field `entries`"));
+    }
+}
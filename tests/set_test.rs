@@ -0,0 +1,84 @@
+extern crate intmap;
+
+use intmap::IntSet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_insert_contains_remove() {
+        let count = 20_000;
+        let mut set = IntSet::new();
+
+        for i in 0..count {
+            assert!(set.insert(i));
+        }
+        assert!(!set.insert(0));
+        assert_eq!(set.len(), count as usize);
+
+        for i in 0..count {
+            assert!(set.contains(i));
+        }
+        assert!(!set.contains(count));
+
+        for i in 0..count {
+            assert!(set.remove(i));
+        }
+        assert!(!set.remove(0));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn set_iter() {
+        let set = (0..100).collect::<IntSet>();
+
+        let mut values: Vec<u64> = set.iter().collect();
+        values.sort();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = (0..100).collect::<IntSet>();
+        let b = (50..150).collect::<IntSet>();
+
+        let mut union: Vec<u64> = a.union(&b).collect();
+        union.sort();
+        assert_eq!(union, (0..150).collect::<Vec<_>>());
+
+        let mut intersection: Vec<u64> = a.intersection(&b).collect();
+        intersection.sort();
+        assert_eq!(intersection, (50..100).collect::<Vec<_>>());
+
+        let mut difference: Vec<u64> = a.difference(&b).collect();
+        difference.sort();
+        assert_eq!(difference, (0..50).collect::<Vec<_>>());
+
+        let empty = IntSet::new();
+        assert_eq!(a.intersection(&empty).count(), 0);
+        assert_eq!(a.difference(&empty).count(), a.len());
+        assert_eq!(empty.union(&a).count(), a.len());
+    }
+
+    #[test]
+    fn set_unit_values_take_no_space() {
+        assert_eq!(std::mem::size_of::<()>(), 0);
+        assert_eq!(std::mem::size_of::<(u64, ())>(), std::mem::size_of::<u64>());
+    }
+
+    #[test]
+    fn set_equality() {
+        let a = (0..1_000).collect::<IntSet>();
+        let b = (0..1_000).rev().collect::<IntSet>();
+
+        assert_eq!(a, b);
+
+        let subset = (0..999).collect::<IntSet>();
+        let superset = (0..1_001).collect::<IntSet>();
+        assert_ne!(a, subset);
+        assert_ne!(subset, a);
+        assert_ne!(a, superset);
+        assert_ne!(superset, a);
+    }
+}